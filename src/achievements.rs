@@ -0,0 +1,356 @@
+use std::collections::BTreeSet;
+
+use crate::*;
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowAchievementsEvent>()
+            .add_system(check_achievements.after(check_for_win))
+            .add_system(remove_toasts)
+            .add_system(show_achievements);
+    }
+}
+
+const TOAST_SECONDS: f64 = 4.0;
+const TOAST_HEIGHT: f32 = 50.0;
+const TOAST_BACKGROUND: Color = Color::rgba(0.9, 0.9, 0.9, 0.95);
+
+pub struct ShowAchievementsEvent;
+
+pub struct Achievement {
+    /// Saved when the achievement is unlocked, so must never change
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub predicate: fn(&AchievementContext) -> bool,
+}
+
+/// What an achievement can be unlocked by
+pub struct AchievementContext<'a> {
+    pub saved_data: &'a SavedData,
+    /// Set in the frame a level is won
+    pub win: Option<&'a LevelWin>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelWin {
+    pub level: GameLevel,
+    pub progress: LevelProgress,
+}
+
+/// How the player went about the current level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelProgress {
+    pub locked_any: bool,
+    pub touch_rotations: bool,
+    pub other_rotations: bool,
+}
+
+pub static ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement {
+        id: "first_win",
+        title: "Balanced",
+        description: "Complete any level",
+        predicate: |x| x.win.is_some(),
+    },
+    Achievement {
+        id: "first_challenge",
+        title: "Daily Dose",
+        description: "Beat a daily challenge",
+        predicate: |x| x.saved_data.stats.levels_completed.challenge >= 1,
+    },
+    Achievement {
+        id: "streak_7",
+        title: "Creature of Habit",
+        description: "Beat the daily challenge 7 days in a row",
+        predicate: |x| x.saved_data.stats.longest_streak >= 7,
+    },
+    Achievement {
+        id: "infinite_15",
+        title: "Skyscraper",
+        description: "Build a tower of 15 shapes in infinite",
+        predicate: |x| x.saved_data.stats.best_infinite_height >= 15,
+    },
+    Achievement {
+        id: "no_locks",
+        title: "Freehand",
+        description: "Win a level of 5 or more shapes without locking any",
+        predicate: |x| {
+            x.win
+                .map(|w| w.level.shapes >= 5 && !w.progress.locked_any)
+                .unwrap_or_default()
+        },
+    },
+    Achievement {
+        id: "touch_rotations",
+        title: "Fingertips",
+        description: "Win a level using only touch to rotate shapes",
+        predicate: |x| {
+            x.win
+                .map(|w| w.progress.touch_rotations && !w.progress.other_rotations)
+                .unwrap_or_default()
+        },
+    },
+    Achievement {
+        id: "speedrun",
+        title: "Against the Clock",
+        description: "Finish a speedrun",
+        predicate: |x| x.saved_data.speedrun.best_run.is_some(),
+    },
+    Achievement {
+        id: "drags_1000",
+        title: "Busy Hands",
+        description: "Drag shapes 1000 times",
+        predicate: |x| x.saved_data.stats.drags >= 1000,
+    },
+];
+
+/// The achievements which have just been unlocked
+pub fn newly_unlocked(
+    context: &AchievementContext,
+    unlocked: &BTreeSet<String>,
+) -> Vec<&'static Achievement> {
+    ACHIEVEMENTS
+        .iter()
+        .filter(|x| !unlocked.contains(x.id) && (x.predicate)(context))
+        .collect()
+}
+
+#[derive(Component)]
+struct AchievementToast {
+    remove_at: f64,
+}
+
+fn check_achievements(
+    mut commands: Commands,
+    mut progress: Local<LevelProgress>,
+    current_level: Res<CurrentLevel>,
+    mut drag_ended_events: EventReader<DragEndedEvent>,
+    mut change_level_events: EventReader<ChangeLevelEvent>,
+    mut rotate_events: EventReader<RotateEvent>,
+    changed_draggables: Query<&Draggable, Changed<Draggable>>,
+    toasts: Query<&AchievementToast>,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for event in rotate_events.iter() {
+        if event.snap_resolution.is_some() {
+            progress.other_rotations = true;
+        } else {
+            progress.touch_rotations = true;
+        }
+    }
+    // Shapes which start the level locked are not counted
+    if !current_level.is_changed() && changed_draggables.iter().any(|x| x.is_locked()) {
+        progress.locked_any = true;
+    }
+
+    let dragged = drag_ended_events.iter().count() > 0;
+    let won = change_level_events
+        .iter()
+        .any(|x| matches!(x, ChangeLevelEvent::Next));
+
+    if dragged || won || current_level.is_changed() {
+        let win = won.then(|| LevelWin {
+            level: current_level.0,
+            progress: progress.clone(),
+        });
+        let saved_data = SavedData::get_or_create(&mut pkv);
+        let context = AchievementContext {
+            saved_data: &saved_data,
+            win: win.as_ref(),
+        };
+        let unlocked = newly_unlocked(&context, &saved_data.achievements);
+
+        if !unlocked.is_empty() {
+            SavedData::update(&mut pkv, |mut x| {
+                x.achievements.extend(unlocked.iter().map(|a| a.id.to_string()));
+                x
+            });
+
+            let now = time.elapsed_seconds_f64();
+            for (index, achievement) in unlocked.into_iter().enumerate() {
+                spawn_toast(
+                    &mut commands,
+                    &asset_server,
+                    achievement,
+                    toasts.iter().count() + index,
+                    now,
+                );
+            }
+        }
+    }
+
+    if current_level.is_changed() {
+        *progress = LevelProgress::default();
+    }
+}
+
+fn spawn_toast(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    achievement: &Achievement,
+    index: usize,
+    now: f64,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.0 + TOAST_HEIGHT * index as f32),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Px(TOAST_HEIGHT)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            z_index: ZIndex::Global(9),
+            ..Default::default()
+        })
+        .insert(AchievementToast {
+            remove_at: now + TOAST_SECONDS,
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    background_color: TOAST_BACKGROUND.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            format!("Achievement unlocked\n{}", achievement.title),
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                                font_size: 16.0,
+                                color: Color::BLACK,
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::CENTER),
+                    );
+                });
+        });
+}
+
+fn remove_toasts(
+    mut commands: Commands,
+    toasts: Query<(Entity, &AchievementToast)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, toast) in toasts.iter() {
+        if toast.remove_at <= now {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn show_achievements(
+    mut commands: Commands,
+    mut events: EventReader<ShowAchievementsEvent>,
+    mut pkv: ResMut<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let unlocked = SavedData::get_or_create(&mut pkv).achievements;
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            format!(
+                "Achievements {}/{}",
+                ACHIEVEMENTS
+                    .iter()
+                    .filter(|x| unlocked.contains(x.id))
+                    .count(),
+                ACHIEVEMENTS.len()
+            ),
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        for achievement in ACHIEVEMENTS.iter() {
+            let is_unlocked = unlocked.contains(achievement.id);
+            let (mark, color) = if is_unlocked {
+                ("[x]", Color::BLACK)
+            } else {
+                ("[ ]", SMALL_TEXT_COLOR)
+            };
+
+            parent.spawn(TextBundle::from_sections([
+                TextSection::new(
+                    format!("{mark} {}\n", achievement.title),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 18.0,
+                        color,
+                    },
+                ),
+                TextSection::new(
+                    format!("    {}", achievement.description),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 12.0,
+                        color: SMALL_TEXT_COLOR,
+                    },
+                ),
+            ]));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_unique() {
+        let ids: BTreeSet<_> = ACHIEVEMENTS.iter().map(|x| x.id).collect();
+        assert_eq!(ids.len(), ACHIEVEMENTS.len());
+    }
+
+    #[test]
+    fn test_newly_unlocked() {
+        let mut saved_data = SavedData::default();
+        saved_data.stats.best_infinite_height = 15;
+        saved_data.achievements.insert("first_win".to_string());
+
+        let win = LevelWin {
+            level: GameLevel {
+                shapes: 15,
+                level_type: LevelType::Infinite { seed: 1 },
+            },
+            progress: LevelProgress {
+                locked_any: true,
+                touch_rotations: true,
+                other_rotations: false,
+            },
+        };
+        let context = AchievementContext {
+            saved_data: &saved_data,
+            win: Some(&win),
+        };
+
+        let ids: Vec<_> = newly_unlocked(&context, &saved_data.achievements)
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        assert_eq!(ids, vec!["infinite_15", "touch_rotations"]);
+    }
+}
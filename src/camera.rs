@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::ZOOM_ENTITY_LAYER;
+use crate::{Settings, ZOOM_ENTITY_LAYER};
 
 pub struct CameraPlugin;
 
@@ -9,7 +9,8 @@ impl Plugin for CameraPlugin {
         app.add_startup_system(setup)
             .add_system_to_stage(CoreStage::PostUpdate, move_zoom_camera)
             .add_system_to_stage(CoreStage::PostUpdate, activate_zoom_camera)
-            .add_system_to_stage(CoreStage::PostUpdate, deactivate_zoom_camera);
+            .add_system_to_stage(CoreStage::PostUpdate, deactivate_zoom_camera)
+            .add_system(apply_zoom_settings);
     }
 }
 
@@ -43,9 +44,10 @@ pub struct TouchDragged;
 
 fn activate_zoom_camera(
     added: Query<Added<TouchDragged>>,
+    settings: Res<Settings>,
     mut cameras: Query<&mut Camera, With<ZoomCamera>>,
 ) {
-    if !added.is_empty() {
+    if !added.is_empty() && settings.zoom_lens {
         for mut c in cameras.iter_mut() {
             c.is_active = true;
         }
@@ -70,6 +72,22 @@ fn deactivate_zoom_camera(
     }
 }
 
+fn apply_zoom_settings(
+    settings: Res<Settings>,
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection, &mut ZoomCamera)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut camera, mut projection, mut zoom_camera) in cameras.iter_mut() {
+        zoom_camera.scale = settings.zoom_scale();
+        projection.scale = settings.zoom_scale();
+        if !settings.zoom_lens {
+            camera.is_active = false;
+        }
+    }
+}
+
 fn move_zoom_camera(
     query: Query<&Transform, (Changed<Transform>, Without<ZoomCamera>, With<TouchDragged>)>,
     mut cameras: Query<(&mut Transform, &ZoomCamera)>,
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use serde::*;
+
+use crate::game_shape::{GameShape, ALL_SHAPES};
+use crate::grid::prelude::Shape;
+use crate::{FloorProfile, JointKind, Platform};
+
+/// The campaign levels, designed in `assets/levels/campaign.json`
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        Self::from_json(include_str!("../assets/levels/campaign.json"))
+            .expect("Could not load campaign")
+    }
+}
+
+impl Campaign {
+    pub fn from_json(json: &str) -> Result<Self, anyhow::Error> {
+        let campaign: Campaign = serde_json::from_str(json)?;
+
+        for (index, level) in campaign.levels.iter().enumerate() {
+            for shape in level.shapes.iter() {
+                if shape.shape.game_shape().is_none() {
+                    anyhow::bail!("Level {} has an unknown shape {:?}", index + 1, shape.shape);
+                }
+            }
+            for joint in level.joints.iter() {
+                let (a, b) = joint.shapes;
+                if a == b || a.max(b) >= level.shapes.len() {
+                    anyhow::bail!(
+                        "Level {} has a joint between missing shapes {:?}",
+                        index + 1,
+                        joint.shapes
+                    );
+                }
+            }
+        }
+        Ok(campaign)
+    }
+
+    pub fn get_level(&self, index: usize) -> Option<&CampaignLevel> {
+        self.levels.get(index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CampaignLevel {
+    /// Text shown at the start of the level
+    #[serde(default)]
+    pub text: Option<String>,
+    pub shapes: Vec<CampaignShape>,
+    #[serde(default)]
+    pub joints: Vec<CampaignJoint>,
+    #[serde(default)]
+    pub platforms: Vec<Platform>,
+    #[serde(default)]
+    pub floor: FloorProfile,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CampaignShape {
+    pub shape: ShapeName,
+    /// The starting position. If missing, a random spawn slot is used
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
+    /// The starting angle in radians. If missing, a random angle is used
+    #[serde(default)]
+    pub angle: Option<f32>,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// Joins two of the shapes of a level where they start
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CampaignJoint {
+    /// The indices of the joined shapes in the level's shapes
+    pub shapes: (usize, usize),
+    pub kind: JointKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum ShapeName {
+    Circle,
+    Triangle,
+    Tetromino(String),
+    Pentomino(String),
+}
+
+impl ShapeName {
+    pub fn game_shape(&self) -> Option<&'static GameShape> {
+        const TETROMINO_START: usize = 2;
+        const PENTOMINO_START: usize = TETROMINO_START + Shape::<4>::TETROMINO_NAMES.len();
+
+        let index = match self {
+            ShapeName::Circle => 0,
+            ShapeName::Triangle => 1,
+            ShapeName::Tetromino(name) => {
+                TETROMINO_START
+                    + Shape::<4>::TETROMINO_NAMES
+                        .iter()
+                        .position(|x| x == name)?
+            }
+            ShapeName::Pentomino(name) => {
+                PENTOMINO_START
+                    + Shape::<5>::FREE_PENTOMINO_NAMES
+                        .iter()
+                        .position(|x| x == name)?
+            }
+        };
+        ALL_SHAPES.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_campaign_loads() {
+        let campaign = Campaign::default();
+        assert!(!campaign.levels.is_empty());
+    }
+
+    #[test]
+    fn test_shape_names() {
+        for shape in ALL_SHAPES.iter() {
+            let name = match shape.index {
+                0 => ShapeName::Circle,
+                1 => ShapeName::Triangle,
+                2..=8 => ShapeName::Tetromino(shape.name.to_string()),
+                _ => ShapeName::Pentomino(shape.name.to_string()),
+            };
+            assert_eq!(name.game_shape().map(|x| x.index), Some(shape.index));
+        }
+    }
+
+    #[test]
+    fn test_unknown_shape() {
+        let result = Campaign::from_json(r#"{"levels":[{"shapes":[{"shape":{"Tetromino":"Q"}}]}]}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_joint_to_missing_shape() {
+        let result = Campaign::from_json(
+            r#"{"levels":[{"shapes":[{"shape":"Circle"}],"joints":[{"shapes":[0,1],"kind":"Fixed"}]}]}"#,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Generates the daily challenge.
+//!
+//! Every player must get the same puzzle on the same day, whatever version of the game they have,
+//! so nothing here depends on `rand`.
+//! Shapes are chosen from a fixed list of indices into `ALL_SHAPES`, so existing shapes must never
+//! be reordered or removed there; new shapes may only be added to the end.
+//! Any change to the output for a date must come with an increase to `CHALLENGE_GENERATOR_VERSION`,
+//! which is recorded with challenge results and replays.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::game_shape::{GameShape, ALL_SHAPES};
+
+/// Version 1 chose the shapes.
+/// Version 2 chooses the same shapes, followed by their spawn slots and angles.
+pub const CHALLENGE_GENERATOR_VERSION: u32 = 2;
+
+/// Indices into `ALL_SHAPES` that the challenge chooses from.
+/// Never change this - add a new pool for a new generator version instead.
+const SHAPE_POOL_V1: [usize; 21] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+];
+
+/// The number of spawn slots shapes are shuffled between
+const SPAWN_SLOTS_V2: usize = 36;
+
+/// The SplitMix64 generator (Steele, Lea & Flood, 2014).
+/// It is tiny, fast and fully specified here so its output can never change.
+#[derive(Debug, Clone)]
+pub struct ChallengeRng(u64);
+
+impl ChallengeRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn from_date(date: NaiveDate) -> Self {
+        Self(date_seed(date))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, found by multiplying rather than with modulo
+    pub fn next_below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/// The date written as a number, e.g. 20230105
+fn date_seed(date: NaiveDate) -> u64 {
+    (date.year().unsigned_abs() as u64 * 10_000) + (date.month() as u64 * 100) + date.day() as u64
+}
+
+#[derive(Debug, Clone)]
+pub struct ChallengeShape {
+    pub shape: &'static GameShape,
+    /// The spawn slot, as used by `get_shape_spawn_position`
+    pub slot: usize,
+    /// The starting angle in whole degrees
+    pub degrees: u32,
+}
+
+impl ChallengeShape {
+    pub fn angle(&self) -> f32 {
+        (self.degrees as f32).to_radians()
+    }
+}
+
+/// The starting board of the challenge for this date
+pub fn challenge_layout(date: NaiveDate, count: usize) -> Vec<ChallengeShape> {
+    generate_layout(ChallengeRng::from_date(date), count)
+}
+
+/// A starting board which depends only on the state of the generator
+pub fn generate_layout(mut rng: ChallengeRng, count: usize) -> Vec<ChallengeShape> {
+    let shapes: Vec<&'static GameShape> = (0..count)
+        .map(|_| &ALL_SHAPES[SHAPE_POOL_V1[rng.next_below(SHAPE_POOL_V1.len())]])
+        .collect();
+
+    // Fisher-Yates shuffle
+    let mut slots: Vec<usize> = (0..SPAWN_SLOTS_V2).collect();
+    for i in (1..slots.len()).rev() {
+        let j = rng.next_below(i + 1);
+        slots.swap(i, j);
+    }
+
+    shapes
+        .into_iter()
+        .zip(slots)
+        .map(|(shape, slot)| ChallengeShape {
+            shape,
+            slot,
+            degrees: rng.next_below(360) as u32,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CHALLENGE_SHAPES;
+    use test_case::test_case;
+
+    #[test]
+    fn test_rng_matches_reference() {
+        let mut rng = ChallengeRng(0);
+
+        assert_eq!(rng.next_u64(), 0xE220A8397B1DCDAF);
+        assert_eq!(rng.next_u64(), 0x6E789E6AA1B965F4);
+        assert_eq!(rng.next_u64(), 0x06C45D188009454F);
+    }
+
+    /// Pins the shape at each index in the pool.
+    /// The size of each shape tells apart tetrominoes and pentominoes with the same name.
+    #[test]
+    fn test_shape_pool() {
+        let shapes: Vec<_> = SHAPE_POOL_V1
+            .iter()
+            .map(|&i| {
+                let shape = &ALL_SHAPES[i];
+                let size = shape
+                    .body
+                    .to_collider_shape(10.0)
+                    .raw
+                    .compute_local_aabb()
+                    .extents();
+                format!("{} {} {:.2}x{:.2}", shape.index, shape.name, size.x, size.y)
+            })
+            .collect();
+        insta::assert_debug_snapshot!(shapes);
+    }
+
+    #[test_case(2023, 1, 1)]
+    #[test_case(2023, 6, 15)]
+    #[test_case(2024, 2, 29)]
+    fn test_challenge_layout(year: i32, month: u32, day: u32) {
+        let date = NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let shapes: Vec<_> = challenge_layout(date, CHALLENGE_SHAPES)
+            .into_iter()
+            .map(|x| {
+                format!(
+                    "{} {} slot {} angle {}",
+                    x.shape.index, x.shape.name, x.slot, x.degrees
+                )
+            })
+            .collect();
+
+        insta::assert_debug_snapshot!(format!("challenge_{date}"), shapes);
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::*;
+
+use crate::challenge::CHALLENGE_GENERATOR_VERSION;
+use crate::screenshots::{SvgFile, SvgSavedEvent};
+use crate::*;
+
+pub struct ChallengeHistoryPlugin;
+
+impl Plugin for ChallengeHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowCalendarEvent>()
+            .add_system(record_challenges)
+            .add_system(show_calendar);
+    }
+}
+
+const CHALLENGE_HISTORY_KEY: &str = "challenge_history";
+const CHALLENGE_ATTEMPTS_KEY: &str = "challenge_attempts";
+/// Older days keep their time but not their picture, which is still in the gallery
+const MAX_CHALLENGE_PICTURES: usize = 31;
+const CALENDAR_CELL_WIDTH: f32 = 44.0;
+const CALENDAR_CELL_HEIGHT: f32 = 32.0;
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+pub struct ShowCalendarEvent;
+
+/// Every daily challenge the player has beaten.
+/// This is where streaks come from; `SavedData::challenge_streak` only seeds it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeHistory {
+    pub days: BTreeMap<NaiveDate, ChallengeDay>,
+}
+
+/// The history is stored wrapped in this so old formats can be migrated, like `SavedData`
+#[derive(Debug, Serialize, Deserialize)]
+enum VersionedChallengeHistory {
+    V1(ChallengeHistory),
+}
+
+impl VersionedChallengeHistory {
+    fn migrate(self) -> ChallengeHistory {
+        match self {
+            VersionedChallengeHistory::V1(history) => history,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeDay {
+    /// Seconds from the start of the level until it was won.
+    /// Missing for days from before the history was kept.
+    #[serde(default)]
+    pub seconds: Option<f64>,
+    /// The final tower, only kept for recent days
+    #[serde(default)]
+    pub file: Option<SvgFile>,
+    /// How many times the challenge was started or reset before it was beaten
+    #[serde(default)]
+    pub attempts: u32,
+    /// The version of the generator which made the challenge, if it was recorded
+    #[serde(default)]
+    pub generator_version: Option<u32>,
+}
+
+/// The number of attempts at a day's challenge
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeAttempts {
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    generator_version: Option<u32>,
+    attempts: u32,
+}
+
+impl ChallengeAttempts {
+    pub fn get(pkv: &PkvStore) -> Self {
+        pkv.get::<Self>(CHALLENGE_ATTEMPTS_KEY).unwrap_or_default()
+    }
+
+    pub fn set(&self, pkv: &mut PkvStore) {
+        if let Err(err) = pkv.set(CHALLENGE_ATTEMPTS_KEY, self) {
+            error!("Could not save challenge attempts: {}", err);
+        }
+    }
+
+    fn load(pkv: &PkvStore, today: NaiveDate) -> u32 {
+        let value = Self::get(pkv);
+        // A new generator makes a different challenge, so earlier attempts don't count
+        if value.date == Some(today) && value.generator_version == Some(CHALLENGE_GENERATOR_VERSION)
+        {
+            value.attempts
+        } else {
+            0
+        }
+    }
+
+    fn increment(pkv: &mut PkvStore, today: NaiveDate) {
+        Self {
+            date: Some(today),
+            generator_version: Some(CHALLENGE_GENERATOR_VERSION),
+            attempts: Self::load(pkv, today) + 1,
+        }
+        .set(pkv);
+    }
+
+    /// Combine with attempts from another device, keeping the latest day
+    #[must_use]
+    pub fn merge(self, other: ChallengeAttempts) -> ChallengeAttempts {
+        if (self.date, self.generator_version) == (other.date, other.generator_version) {
+            Self {
+                attempts: self.attempts.max(other.attempts),
+                ..self
+            }
+        } else if (other.date, other.generator_version) > (self.date, self.generator_version) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl ChallengeHistory {
+    /// Load the history in any known format.
+    /// Histories from before it was versioned are seeded with the streak in `saved_data`.
+    /// `None` if there is a history which can't be read, which must not be overwritten.
+    fn try_load(source: &impl SaveSource, saved_data: &SavedData) -> Option<Self> {
+        if let Some(versioned) = source.load::<VersionedChallengeHistory>() {
+            return Some(versioned.migrate());
+        }
+
+        let mut history = if let Some(unversioned) = source.load::<ChallengeHistory>() {
+            unversioned
+        } else if source.has_data() {
+            return None;
+        } else {
+            Self::default()
+        };
+        history.seed(saved_data);
+        Some(history)
+    }
+
+    pub fn load(pkv: &PkvStore) -> Self {
+        let saved_data = SavedData::read(pkv);
+        Self::try_load(&StoredValue(pkv, CHALLENGE_HISTORY_KEY), &saved_data).unwrap_or_default()
+    }
+
+    pub fn update<F: FnOnce(ChallengeHistory) -> ChallengeHistory>(pkv: &mut PkvStore, f: F) {
+        let saved_data = SavedData::read(pkv);
+        let source = StoredValue(pkv, CHALLENGE_HISTORY_KEY);
+        let Some(history) = Self::try_load(&source, &saved_data) else {
+            error!("The challenge history could not be read so it will not be overwritten");
+            return;
+        };
+        let mut history = f(history);
+        history.forget_old_pictures();
+        if let Err(err) = pkv.set(
+            CHALLENGE_HISTORY_KEY,
+            &VersionedChallengeHistory::V1(history),
+        ) {
+            error!("Could not save challenge history: {}", err);
+        }
+    }
+
+    pub fn add(pkv: &mut PkvStore, date: NaiveDate, day: ChallengeDay) {
+        Self::update(pkv, |mut history| {
+            history.insert(date, day);
+            history
+        });
+    }
+
+    pub fn to_versioned_json(&self) -> serde_json::Value {
+        serde_json::to_value(VersionedChallengeHistory::V1(self.clone()))
+            .expect("Could not serialize challenge history")
+    }
+
+    /// Histories from before it was versioned are seeded from `saved_data`
+    pub fn from_versioned_json(value: &serde_json::Value, saved_data: &SavedData) -> Option<Self> {
+        Self::try_load(value, saved_data)
+    }
+
+    /// Combine with the history from another device, keeping the fastest time for each day
+    #[must_use]
+    pub fn merge(mut self, other: ChallengeHistory) -> ChallengeHistory {
+        for (date, day) in other.days {
+            self.insert(date, day);
+        }
+        self
+    }
+
+    /// Add the days of the streak recorded before the history was kept
+    fn seed(&mut self, saved_data: &SavedData) {
+        let Some(last) = saved_data.last_challenge else {return;};
+
+        for offset in 0..saved_data.challenge_streak.max(1) {
+            let Some(date) = last.checked_sub_days(Days::new(offset as u64)) else {break;};
+            self.days.entry(date).or_default();
+        }
+    }
+
+    /// Record a completed challenge, keeping the fastest time for each day.
+    /// Times for a challenge from a newer generator replace older ones.
+    /// A day without a time never replaces one with a time.
+    fn insert(&mut self, date: NaiveDate, day: ChallengeDay) {
+        let Some(existing) = self.days.get(&date) else {
+            self.days.insert(date, day);
+            return;
+        };
+        let keep_existing = match (existing.seconds, day.seconds) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(existing_seconds), Some(seconds)) => {
+                if existing.generator_version == day.generator_version {
+                    existing_seconds <= seconds
+                } else {
+                    existing.generator_version > day.generator_version
+                }
+            }
+        };
+        if !keep_existing {
+            self.days.insert(date, day);
+        }
+    }
+
+    fn forget_old_pictures(&mut self) {
+        for day in self.days.values_mut().rev().skip(MAX_CHALLENGE_PICTURES) {
+            day.file = None;
+        }
+    }
+
+    /// The number of consecutive days up to today on which the challenge was beaten.
+    /// A streak is not broken until the end of today.
+    pub fn current_streak(&self, today: NaiveDate) -> usize {
+        if self.days.contains_key(&today) {
+            self.streak_ending(today)
+        } else {
+            today
+                .checked_sub_days(Days::new(1))
+                .map(|yesterday| self.streak_ending(yesterday))
+                .unwrap_or_default()
+        }
+    }
+
+    /// The streak there will be once the challenge on `date` is beaten
+    pub fn streak_after_beating(&self, date: NaiveDate) -> usize {
+        1 + date
+            .checked_sub_days(Days::new(1))
+            .map(|yesterday| self.streak_ending(yesterday))
+            .unwrap_or_default()
+    }
+
+    /// The number of consecutive days ending on `date` on which the challenge was beaten
+    fn streak_ending(&self, date: NaiveDate) -> usize {
+        let mut streak = 0;
+        let mut date = date;
+        while self.days.contains_key(&date) {
+            streak += 1;
+            match date.checked_sub_days(Days::new(1)) {
+                Some(previous) => date = previous,
+                None => break,
+            }
+        }
+        streak
+    }
+
+    pub fn longest_streak(&self) -> usize {
+        let mut longest = 0;
+        let mut current = 0;
+        let mut previous: Option<NaiveDate> = None;
+
+        for &date in self.days.keys() {
+            current = match previous {
+                Some(p) if p.checked_add_days(Days::new(1)) == Some(date) => current + 1,
+                _ => 1,
+            };
+            longest = longest.max(current);
+            previous = Some(date);
+        }
+        longest
+    }
+}
+
+/// The weeks of a month, starting on Monday, with the day of the month in each cell
+fn month_weeks(year: i32, month: u32) -> Vec<[Option<u32>; 7]> {
+    let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {return vec![];};
+    let offset = first.weekday().num_days_from_monday() as usize;
+
+    let mut weeks = vec![];
+    let mut week = [None; 7];
+    let mut date = first;
+    let mut column = offset;
+
+    while date.month() == month {
+        week[column] = Some(date.day());
+        column += 1;
+        if column == 7 {
+            weeks.push(week);
+            week = [None; 7];
+            column = 0;
+        }
+        match date.succ_opt() {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+    if column > 0 {
+        weeks.push(week);
+    }
+    weeks
+}
+
+fn record_challenges(
+    mut events: EventReader<SvgSavedEvent>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    mut started: Local<Option<f64>>,
+    mut pkv: ResMut<PkvStore>,
+    playback: Res<ReplayPlayback>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let today = get_today_date();
+
+    for event in events.iter() {
+        if event.level.level_type == LevelType::Challenge {
+            let attempts = ChallengeAttempts::load(&pkv, today).max(1);
+            ChallengeHistory::add(
+                &mut pkv,
+                today,
+                ChallengeDay {
+                    seconds: started.map(|s| now - s),
+                    file: Some(event.file.clone()),
+                    attempts,
+                    generator_version: Some(CHALLENGE_GENERATOR_VERSION),
+                },
+            );
+        }
+    }
+
+    if current_level.is_changed() {
+        *started = match current_level.0.level_type {
+            LevelType::Challenge => Some(now),
+            _ => None,
+        };
+        if started.is_some() && !playback.is_active() {
+            ChallengeAttempts::increment(&mut pkv, today);
+        }
+    }
+}
+
+fn show_calendar(
+    mut commands: Commands,
+    mut events: EventReader<ShowCalendarEvent>,
+    pkv: Res<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let history = ChallengeHistory::load(&pkv);
+    let today = get_today_date();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let text_style = |color: Color| TextStyle {
+        font: font.clone(),
+        font_size: 16.0,
+        color,
+    };
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            today.format("%B %Y").to_string(),
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        let header = WEEKDAYS.map(|x| (x.to_string(), false, false));
+        let weeks = month_weeks(today.year(), today.month()).into_iter().map(|week| {
+            week.map(|day| match day.and_then(|d| today.with_day(d)) {
+                Some(date) => (
+                    date.day().to_string(),
+                    history.days.contains_key(&date),
+                    date == today,
+                ),
+                None => (String::new(), false, false),
+            })
+        });
+
+        for row in std::iter::once(header).chain(weeks) {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (text, beaten, is_today) in row {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        Val::Px(CALENDAR_CELL_WIDTH),
+                                        Val::Px(CALENDAR_CELL_HEIGHT),
+                                    ),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                background_color: if beaten {
+                                    BACKGROUND_COLOR.into()
+                                } else {
+                                    Color::NONE.into()
+                                },
+                                ..Default::default()
+                            })
+                            .with_children(|parent| {
+                                let color = if is_today {
+                                    Color::BLACK
+                                } else {
+                                    SMALL_TEXT_COLOR
+                                };
+                                parent.spawn(TextBundle::from_section(text, text_style(color)));
+                            });
+                    }
+                });
+        }
+
+        let mut lines = vec![
+            format!("Current streak: {}", history.current_streak(today)),
+            format!("Longest streak: {}", history.longest_streak()),
+        ];
+        if let Some(seconds) = history.days.get(&today).and_then(|x| x.seconds) {
+            let seconds = seconds.round() as u64;
+            lines.push(format!("Today: {}m {:02}s", seconds / 60, seconds % 60));
+        }
+
+        for line in lines {
+            parent.spawn(TextBundle::from_section(line, text_style(SMALL_TEXT_COLOR)));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(dates: &[(i32, u32, u32)]) -> ChallengeHistory {
+        let mut history = ChallengeHistory::default();
+        for &(y, m, d) in dates {
+            history.insert(
+                NaiveDate::from_ymd_opt(y, m, d).unwrap(),
+                ChallengeDay {
+                    seconds: Some(60.0),
+                    file: Some(SvgFile {
+                        title: String::new(),
+                        svg: String::new(),
+                    }),
+                    attempts: 1,
+                    generator_version: Some(CHALLENGE_GENERATOR_VERSION),
+                },
+            );
+        }
+        history
+    }
+
+    #[test]
+    fn test_streaks() {
+        let history = history(&[
+            (2023, 1, 1),
+            (2023, 1, 2),
+            (2023, 1, 3),
+            (2023, 1, 10),
+            (2023, 1, 11),
+        ]);
+        let date = |d| NaiveDate::from_ymd_opt(2023, 1, d).unwrap();
+
+        assert_eq!(history.longest_streak(), 3);
+        assert_eq!(history.current_streak(date(11)), 2);
+        assert_eq!(history.current_streak(date(12)), 2);
+        assert_eq!(history.current_streak(date(13)), 0);
+        assert_eq!(history.streak_after_beating(date(12)), 3);
+        assert_eq!(history.streak_after_beating(date(13)), 1);
+    }
+
+    #[test]
+    fn test_unversioned_history_is_seeded_from_saved_data() {
+        let unversioned = serde_json::json!({
+            "days": {"2023-01-05": {"seconds": 30.0, "file": {"title": "", "svg": ""}}}
+        });
+        let saved_data = SavedData {
+            challenge_streak: 3,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 1, 5),
+            ..Default::default()
+        };
+        let date = |d| NaiveDate::from_ymd_opt(2023, 1, d).unwrap();
+
+        let history = ChallengeHistory::try_load(&unversioned, &saved_data).unwrap();
+
+        assert_eq!(history.current_streak(date(5)), 3);
+        assert_eq!(history.days[&date(5)].seconds, Some(30.0));
+        assert_eq!(history.days[&date(3)].seconds, None);
+
+        let seeded = ChallengeHistory::try_load(&serde_json::Value::Null, &saved_data).unwrap();
+        assert_eq!(seeded.current_streak(date(5)), 3);
+    }
+
+    #[test]
+    fn test_merge_keeps_recorded_times() {
+        let recorded = history(&[(2023, 1, 4), (2023, 1, 5)]);
+        let mut seeded = ChallengeHistory::default();
+        seeded.seed(&SavedData {
+            challenge_streak: 3,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 1, 5),
+            ..Default::default()
+        });
+
+        let merged = recorded.clone().merge(seeded.clone());
+        assert_eq!(merged, seeded.merge(recorded.clone()));
+        assert_eq!(merged.days.len(), 3);
+        for (date, day) in recorded.days {
+            assert_eq!(merged.days[&date], day);
+        }
+    }
+
+    #[test]
+    fn test_unknown_version_is_not_loaded() {
+        let newer = serde_json::json!({ "V999": { "days": {} } });
+
+        assert_eq!(
+            ChallengeHistory::try_load(&newer, &SavedData::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_old_pictures_are_forgotten() {
+        let dates: Vec<_> = (1..=(MAX_CHALLENGE_PICTURES as u32 + 2))
+            .map(|d| (2023, 1, d))
+            .collect();
+        let mut history = history(&dates);
+        history.forget_old_pictures();
+
+        let pictures: Vec<bool> = history.days.values().map(|x| x.file.is_some()).collect();
+        assert_eq!(pictures[..2], [false, false]);
+        assert!(pictures[2..].iter().all(|&x| x));
+    }
+
+    #[test]
+    fn test_month_weeks() {
+        // February 2023 starts on a Wednesday and has 28 days
+        let weeks = month_weeks(2023, 2);
+
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0], [None, None, Some(1), Some(2), Some(3), Some(4), Some(5)]);
+        assert_eq!(weeks[4], [Some(27), Some(28), None, None, None, None, None]);
+    }
+}
@@ -1,4 +1,6 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
 
 pub const BACKGROUND_COLOR : Color = Color::hsla(216., 0.7,0.72, 1.0);
 
@@ -17,4 +19,46 @@ pub fn choose_color(index: usize)-> Color{
     let saturation = SATURATIONS[(index % (LIGHTNESSES.len() * SATURATIONS.len())) / SATURATIONS.len()];
     let alpha = 1.0;
     Color::hsla(hue, saturation, lightness, alpha)
-}
\ No newline at end of file
+}
+
+/// The colours shapes are drawn in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum Palette {
+    #[default]
+    Default,
+    Pastel,
+    /// The Okabe-Ito colours, which can be told apart with any kind of colour blindness
+    ColourBlind,
+}
+
+impl Palette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Default => "Default",
+            Palette::Pastel => "Pastel",
+            Palette::ColourBlind => "Colour blind",
+        }
+    }
+
+    pub fn choose_color(&self, index: usize) -> Color {
+        match self {
+            Palette::Default => choose_color(index),
+            Palette::Pastel => {
+                let Color::Hsla { hue, .. } = choose_color(index).as_hsla() else {unreachable!()};
+                Color::hsla(hue, 0.6, 0.75, 1.0)
+            }
+            Palette::ColourBlind => {
+                const OKABE_ITO: [Color; 7] = [
+                    Color::rgb(0.90, 0.62, 0.0),
+                    Color::rgb(0.34, 0.71, 0.91),
+                    Color::rgb(0.0, 0.62, 0.45),
+                    Color::rgb(0.94, 0.89, 0.26),
+                    Color::rgb(0.0, 0.45, 0.70),
+                    Color::rgb(0.84, 0.37, 0.0),
+                    Color::rgb(0.80, 0.47, 0.65),
+                ];
+                OKABE_ITO[index % OKABE_ITO.len()]
+            }
+        }
+    }
+}
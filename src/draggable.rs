@@ -47,7 +47,7 @@ impl Plugin for DragPlugin {
 pub const MAX_VELOCITY: f32 = 1000.0;
 pub const LOCK_VELOCITY: f32 = 50.0;
 
-fn handle_rotate_events(
+pub fn handle_rotate_events(
     mut ev_rotate: EventReader<RotateEvent>,
     mut dragged: Query<(&mut Transform, &Draggable)>,
 ) {
@@ -123,6 +123,7 @@ pub fn drag_move(
     mut dragged_entities: Query<(&Draggable, &mut DesiredTranslation), Without<ZoomCamera>>,
     mut touch_rotate: ResMut<TouchRotateResource>,
     mut ev_rotate: EventWriter<RotateEvent>,
+    play_area: Res<PlayArea>,
 ) {
     for event in er_drag_move.iter() {
         debug!("{:?}", event);
@@ -130,17 +131,8 @@ pub fn drag_move(
             .iter_mut()
             .find(|d| d.0.has_drag_source(event.drag_source))
         {
-            let max_x: f32 = crate::WINDOW_WIDTH / 2.0; //You can't leave the game area
-            let max_y: f32 = crate::WINDOW_HEIGHT / 2.0;
-
-            let min_x: f32 = -max_x;
-            let min_y: f32 = -max_y;
-
-            let clamped_position = bevy::math::Vec2::clamp(
-                event.new_position,
-                Vec2::new(min_x, min_y),
-                Vec2::new(max_x, max_y),
-            );
+            //You can't leave the game area
+            let clamped_position = play_area.clamp(event.new_position);
 
             let new_position = (draggable.get_offset() + clamped_position).extend(0.0);
 
@@ -170,32 +162,60 @@ pub fn drag_move(
 pub fn drag_start(
     mut er_drag_start: EventReader<DragStartEvent>,
     rapier_context: Res<RapierContext>,
-    mut draggables: Query<(&mut Draggable, &Transform), Without<ZoomCamera>>,
+    mut draggables: Query<(Entity, &mut Draggable, &Transform, &Velocity), Without<ZoomCamera>>,
     mut touch_rotate: ResMut<TouchRotateResource>,
+    mut undo_history: ResMut<UndoHistory>,
 ) {
     for event in er_drag_start.iter() {
         debug!("Drag Started {:?}", event);
 
-        if draggables.iter().all(|x| !x.0.is_dragged()) {
-            rapier_context.intersections_with_point(event.position, default(), |entity| {
-                if let Ok((mut draggable, transform)) = draggables.get_mut(entity) {
-                    debug!("{:?} found intersection with {:?}", event, draggable);
+        if draggables.iter().all(|x| !x.1.is_dragged()) {
+            let snapshot = BoardSnapshot(
+                draggables
+                    .iter()
+                    .map(|(entity, draggable, transform, velocity)| ShapeSnapshot {
+                        entity,
+                        transform: *transform,
+                        velocity: *velocity,
+                        draggable: draggable.clone(),
+                    })
+                    .collect(),
+            );
+            let mut picked_up = false;
+
+            let mut target = event.target;
+            if target.is_none() {
+                rapier_context.intersections_with_point(event.position, default(), |entity| {
+                    if draggables.contains(entity) {
+                        target = Some(entity);
+                        return false; //Stop looking for intersections
+                    }
+                    true //keep looking for intersections
+                });
+            }
 
-                    let origin = transform.translation.truncate();
-                    let offset = origin - event.position;
+            if let Some((_, mut draggable, transform, _)) =
+                target.and_then(|e| draggables.get_mut(e).ok())
+            {
+                debug!("{:?} picked up {:?}", event, draggable);
 
-                    *draggable = Draggable::Dragged(Dragged {
-                        origin,
-                        offset,
-                        drag_source: event.drag_source,
-                    });
+                let origin = transform.translation.truncate();
+                let offset = origin - event.position;
 
-                    return false; //Stop looking for intersections
-                }
-                true //keep looking for intersections
-            });
+                *draggable = Draggable::Dragged(Dragged {
+                    origin,
+                    offset,
+                    drag_source: event.drag_source,
+                });
+                picked_up = true;
+            }
+
+            if picked_up {
+                undo_history.push(snapshot);
+            }
         } else if let DragSource::Touch { touch_id } = event.drag_source {
-            if let Some((_, transform)) = draggables.iter().find(|x| x.0.touch_id().is_some()) {
+            if let Some((_, _, transform, _)) = draggables.iter().find(|x| x.1.touch_id().is_some())
+            {
                 *touch_rotate = TouchRotateResource(Some(TouchRotate {
                     previous: event.position,
                     centre: transform.translation.truncate(),
@@ -368,6 +388,12 @@ pub struct Dragged {
 #[derive(Resource, Default)]
 pub struct TouchRotateResource(Option<TouchRotate>);
 
+impl TouchRotateResource {
+    pub fn is_rotating(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct TouchRotate {
     pub previous: Vec2,
@@ -384,6 +410,8 @@ pub struct RotateEvent {
 pub struct DragStartEvent {
     pub drag_source: DragSource,
     pub position: Vec2,
+    /// The shape to pick up. If `None`, the shape at `position` is picked up.
+    pub target: Option<Entity>,
 }
 
 #[derive(Debug)]
@@ -400,10 +428,12 @@ pub struct DragEndEvent {
 #[derive(Debug)]
 pub struct DragEndedEvent {}
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum DragSource {
     Mouse,
     Touch { touch_id: u64 },
+    Gamepad { id: usize },
+    Keyboard,
 }
 
 impl DragSource {
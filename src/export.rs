@@ -0,0 +1,363 @@
+use anyhow::bail;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::*;
+
+use crate::*;
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportSaveEvent>()
+            .add_event::<ImportSaveEvent>()
+            .add_event::<ImportSaveCodeEvent>()
+            .add_system(export_save)
+            .add_system(import_save)
+            .add_system(import_save_code);
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_system(poll_uploaded_save_codes);
+    }
+}
+
+/// The prefix of every save code. Change this if the code format changes.
+const SAVE_CODE_PREFIX: &str = "EQ1";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_FILE_NAME: &str = "equilibrium-save.txt";
+
+pub struct ExportSaveEvent;
+pub struct ImportSaveEvent;
+pub struct ImportSaveCodeEvent(pub String);
+
+/// Everything in an exported save. Each store is in its versioned format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedSave {
+    pub user: serde_json::Value,
+    /// Missing from codes exported before the gallery was included
+    #[serde(default)]
+    pub gallery: serde_json::Value,
+    /// Missing from codes exported before the challenge history was included
+    #[serde(default)]
+    pub challenge_history: serde_json::Value,
+    #[serde(default)]
+    pub challenge_attempts: Option<ChallengeAttempts>,
+}
+
+impl ExportedSave {
+    pub fn new(
+        saved_data: &SavedData,
+        gallery: &Gallery,
+        challenge_history: &ChallengeHistory,
+        challenge_attempts: &ChallengeAttempts,
+    ) -> Self {
+        Self {
+            user: saved_data.to_versioned_json(),
+            gallery: gallery.to_versioned_json(),
+            challenge_history: challenge_history.to_versioned_json(),
+            challenge_attempts: Some(challenge_attempts.clone()),
+        }
+    }
+
+    /// Everything saved on this device
+    pub fn load(pkv: &PkvStore) -> Self {
+        Self::new(
+            &SavedData::read(pkv),
+            &Gallery::load(pkv),
+            &ChallengeHistory::load(pkv),
+            &ChallengeAttempts::get(pkv),
+        )
+    }
+
+    pub fn saved_data(&self) -> Option<SavedData> {
+        SavedData::from_versioned_json(&self.user)
+    }
+
+    pub fn gallery(&self) -> Option<Gallery> {
+        Gallery::from_versioned_json(&self.gallery)
+    }
+
+    pub fn challenge_history(&self) -> Option<ChallengeHistory> {
+        ChallengeHistory::from_versioned_json(&self.challenge_history, &self.saved_data()?)
+    }
+
+    /// Merge everything into what is saved on this device
+    pub fn merge_into(self, pkv: &mut ResMut<PkvStore>) {
+        if let Some(imported) = self.saved_data() {
+            SavedData::update(pkv, |x| x.merge(imported));
+        }
+        if let Some(imported) = self.gallery() {
+            Gallery::update(pkv, |x| x.merge(imported));
+        }
+        if let Some(imported) = self.challenge_history() {
+            ChallengeHistory::update(pkv, |x| x.merge(imported));
+        }
+        if let Some(imported) = self.challenge_attempts {
+            ChallengeAttempts::get(pkv).merge(imported).set(pkv);
+        }
+    }
+
+    /// Encode as a string which can be copied between devices
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_string(self).expect("Could not serialize save");
+        let checksum = checksum(json.as_bytes());
+        let data = URL_SAFE_NO_PAD.encode(json);
+        format!("{SAVE_CODE_PREFIX}.{checksum:08x}.{data}")
+    }
+
+    pub fn decode(code: &str) -> Result<Self, anyhow::Error> {
+        let mut parts = code.trim().splitn(3, '.');
+        let (Some(prefix), Some(expected_checksum), Some(data)) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("Save code is incomplete");
+        };
+        if prefix != SAVE_CODE_PREFIX {
+            bail!("Unknown save code format '{prefix}'");
+        }
+
+        let expected_checksum = u32::from_str_radix(expected_checksum, 16)?;
+        let json = URL_SAFE_NO_PAD.decode(data)?;
+        if checksum(&json) != expected_checksum {
+            bail!("Save code checksum does not match");
+        }
+
+        let exported: ExportedSave = serde_json::from_slice(&json)?;
+        if exported.saved_data().is_none()
+            || exported.gallery().is_none()
+            || exported.challenge_history().is_none()
+        {
+            bail!("Save code contains unknown data");
+        }
+        Ok(exported)
+    }
+}
+
+/// Where files are written on desktop builds: the home directory if there is one
+#[cfg(not(target_arch = "wasm32"))]
+pub fn desktop_file_path(file_name: &str) -> std::path::PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(std::path::PathBuf::from)
+        .unwrap_or_default()
+        .join(file_name)
+}
+
+/// 32 bit FNV-1a hash
+fn checksum(bytes: &[u8]) -> u32 {
+    const OFFSET_BASIS: u32 = 0x811c9dc5;
+    const PRIME: u32 = 0x01000193;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ (*byte as u32)).wrapping_mul(PRIME)
+    })
+}
+
+fn export_save(
+    mut commands: Commands,
+    mut events: EventReader<ExportSaveEvent>,
+    pkv: Res<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let code = ExportedSave::load(&pkv).encode();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::wasm::download::download_bytes(
+            "equilibrium-save.txt".into(),
+            code.into_bytes(),
+            "text/plain",
+        );
+        show_message(&mut commands, &panels, &asset_server, "Save exported");
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = desktop_file_path(SAVE_FILE_NAME);
+        if let Err(err) = std::fs::write(&path, code) {
+            error!("Could not write {}: {}", path.display(), err);
+            let message = "Could not export save";
+            show_message(&mut commands, &panels, &asset_server, message);
+            return;
+        }
+        info!("Exported save to {}", path.display());
+        let message = format!("Save exported to\n{}", path.display());
+        show_message(&mut commands, &panels, &asset_server, &message);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn import_save(mut events: EventReader<ImportSaveEvent>) {
+    if events.iter().last().is_some() {
+        crate::wasm::download::pick_text_file();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_save(
+    mut events: EventReader<ImportSaveEvent>,
+    mut code_events: EventWriter<ImportSaveCodeEvent>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let path = desktop_file_path(SAVE_FILE_NAME);
+    match std::fs::read_to_string(&path) {
+        Ok(code) => code_events.send(ImportSaveCodeEvent(code)),
+        Err(err) => error!("Could not read {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn poll_uploaded_save_codes(mut code_events: EventWriter<ImportSaveCodeEvent>) {
+    while let Some(code) = crate::wasm::download::pop_uploaded_text() {
+        code_events.send(ImportSaveCodeEvent(code));
+    }
+}
+
+fn import_save_code(
+    mut commands: Commands,
+    mut events: EventReader<ImportSaveCodeEvent>,
+    mut pkv: ResMut<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    for event in events.iter() {
+        let message = match ExportedSave::decode(&event.0) {
+            Ok(exported) => {
+                exported.merge_into(&mut pkv);
+                "Save imported".to_string()
+            }
+            Err(err) => {
+                warn!("Could not import save: {}", err);
+                format!("Could not import save:\n{err}")
+            }
+        };
+
+        show_message(&mut commands, &panels, &asset_server, &message);
+    }
+}
+
+fn show_message(
+    commands: &mut Commands,
+    panels: &Query<Entity, With<Panel>>,
+    asset_server: &AssetServer,
+    message: &str,
+) {
+    spawn_panel(commands, panels, |parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                message,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 20.0,
+                    color: SMALL_TEXT_COLOR,
+                },
+            )
+            .with_text_alignment(TextAlignment::CENTER),
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screenshots::SvgFile;
+    use chrono::NaiveDate;
+
+    fn saved_data() -> SavedData {
+        SavedData {
+            tutorial_finished: true,
+            challenge_streak: 5,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 1, 10),
+            ..Default::default()
+        }
+    }
+
+    fn exported() -> ExportedSave {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 10).unwrap();
+        let file = SvgFile {
+            title: "Equilibrium Challenge 2023-01-10".to_string(),
+            svg: "<svg></svg>".to_string(),
+        };
+
+        let gallery = Gallery {
+            entries: vec![GalleryEntry {
+                file: file.clone(),
+                date,
+                mode: "Challenge".to_string(),
+                shapes: 7,
+            }],
+        };
+        let mut history = ChallengeHistory::default();
+        history.days.insert(
+            date,
+            ChallengeDay {
+                seconds: Some(42.0),
+                file: Some(file),
+                attempts: 2,
+                generator_version: Some(2),
+            },
+        );
+
+        ExportedSave::new(
+            &saved_data(),
+            &gallery,
+            &history,
+            &ChallengeAttempts::default(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let exported = exported();
+        let decoded = ExportedSave::decode(&exported.encode()).unwrap();
+
+        assert_eq!(decoded.saved_data(), Some(saved_data()));
+        assert_eq!(decoded.gallery(), exported.gallery());
+        assert_eq!(decoded.gallery().unwrap().entries.len(), 1);
+        let history = decoded.challenge_history().unwrap();
+        assert_eq!(Some(&history), exported.challenge_history().as_ref());
+        assert_eq!(history.days.values().next().unwrap().seconds, Some(42.0));
+        assert_eq!(decoded.challenge_attempts, exported.challenge_attempts);
+    }
+
+    #[test]
+    fn test_code_without_gallery_or_history() {
+        let exported = ExportedSave {
+            user: saved_data().to_versioned_json(),
+            gallery: serde_json::Value::Null,
+            challenge_history: serde_json::Value::Null,
+            challenge_attempts: None,
+        };
+        let decoded = ExportedSave::decode(&exported.encode()).unwrap();
+
+        assert_eq!(decoded.gallery(), Some(Gallery::default()));
+        // The history is seeded with the streak in the saved data
+        assert_eq!(
+            decoded
+                .challenge_history()
+                .unwrap()
+                .current_streak(NaiveDate::from_ymd_opt(2023, 1, 10).unwrap()),
+            5
+        );
+    }
+
+    #[test]
+    fn test_tampered_code_is_rejected() {
+        let code = exported().encode();
+        let mut tampered = code.into_bytes();
+        let last = tampered.len() - 2;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+
+        assert!(ExportedSave::decode(&String::from_utf8(tampered).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_garbage_is_rejected() {
+        assert!(ExportedSave::decode("hello world").is_err());
+        assert!(ExportedSave::decode("EQ1.00000000.").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use chrono::NaiveDate;
+use serde::*;
+
+use crate::screenshots::{download_png, string_to_image, SvgFile, SvgSavedEvent};
+use crate::*;
+
+pub struct GalleryPlugin;
+
+impl Plugin for GalleryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowGalleryEvent>()
+            .add_system(add_to_gallery)
+            .add_system(show_gallery)
+            .add_system(scroll_gallery)
+            .add_system(gallery_button_system.after(scroll_gallery));
+    }
+}
+
+const GALLERY_KEY: &str = "gallery";
+/// The oldest pictures are removed after this, so the store stays within the browser's quota
+const MAX_GALLERY_ENTRIES: usize = 50;
+const THUMBNAIL_SIZE: f32 = 80.0;
+const SCROLL_LINE_HEIGHT: f32 = 20.0;
+/// A press on a picture only counts as a tap if the gallery scrolled less than this
+const TAP_DISTANCE: f32 = 10.0;
+
+pub struct ShowGalleryEvent;
+
+/// Pictures of every tower the player has completed
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Gallery {
+    pub entries: Vec<GalleryEntry>,
+}
+
+/// The gallery is stored wrapped in this so later formats can be migrated, like `SavedData`
+#[derive(Debug, Serialize, Deserialize)]
+enum VersionedGallery {
+    V1(Gallery),
+}
+
+impl VersionedGallery {
+    fn migrate(self) -> Gallery {
+        match self {
+            VersionedGallery::V1(gallery) => gallery,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GalleryEntry {
+    pub file: SvgFile,
+    pub date: NaiveDate,
+    pub mode: String,
+    pub shapes: usize,
+}
+
+impl GalleryEntry {
+    pub fn new(file: SvgFile, level: &GameLevel) -> Self {
+        Self {
+            file,
+            date: get_today_date(),
+            mode: level.level_type.name().to_string(),
+            shapes: level.shapes,
+        }
+    }
+}
+
+impl Gallery {
+    /// Load the gallery in any known format.
+    /// `None` if there is a gallery which can't be read, which must not be overwritten.
+    fn try_load(source: &impl SaveSource) -> Option<Self> {
+        if let Some(versioned) = source.load::<VersionedGallery>() {
+            Some(versioned.migrate())
+        } else if source.has_data() {
+            None
+        } else {
+            Some(Self::default())
+        }
+    }
+
+    pub fn load(pkv: &PkvStore) -> Self {
+        Self::try_load(&StoredValue(pkv, GALLERY_KEY)).unwrap_or_default()
+    }
+
+    pub fn update<F: FnOnce(Gallery) -> Gallery>(pkv: &mut PkvStore, f: F) {
+        let Some(gallery) = Self::try_load(&StoredValue(pkv, GALLERY_KEY)) else {
+            error!("The gallery could not be read so it will not be overwritten");
+            return;
+        };
+        let mut gallery = f(gallery);
+        gallery.forget_oldest();
+        if let Err(err) = pkv.set(GALLERY_KEY, &VersionedGallery::V1(gallery)) {
+            error!("Could not save gallery: {}", err);
+        }
+    }
+
+    /// Add a picture to the saved gallery
+    pub fn add(pkv: &mut PkvStore, entry: GalleryEntry) {
+        Self::update(pkv, |mut gallery| {
+            gallery.entries.push(entry);
+            gallery
+        });
+    }
+
+    pub fn to_versioned_json(&self) -> serde_json::Value {
+        serde_json::to_value(VersionedGallery::V1(self.clone()))
+            .expect("Could not serialize gallery")
+    }
+
+    pub fn from_versioned_json(value: &serde_json::Value) -> Option<Self> {
+        Self::try_load(value)
+    }
+
+    /// Combine with a gallery from another device, oldest pictures first
+    #[must_use]
+    pub fn merge(mut self, other: Gallery) -> Gallery {
+        for entry in other.entries {
+            if !self.entries.contains(&entry) {
+                self.entries.push(entry);
+            }
+        }
+        self.entries.sort_by_key(|x| x.date);
+        self.forget_oldest();
+        self
+    }
+
+    fn forget_oldest(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_GALLERY_ENTRIES);
+        self.entries.drain(..excess);
+    }
+}
+
+fn add_to_gallery(mut events: EventReader<SvgSavedEvent>, mut pkv: ResMut<PkvStore>) {
+    for event in events.iter() {
+        Gallery::add(&mut pkv, GalleryEntry::new(event.file.clone(), &event.level));
+    }
+}
+
+#[derive(Component, Default)]
+struct GalleryList {
+    position: f32,
+}
+
+#[derive(Component)]
+struct GalleryEntryButton {
+    index: usize,
+    /// The scroll position when this was pressed
+    pressed_at: Option<f32>,
+}
+
+fn show_gallery(
+    mut commands: Commands,
+    mut events: EventReader<ShowGalleryEvent>,
+    pkv: Res<PkvStore>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let gallery = Gallery::load(&pkv);
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Gallery",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        if gallery.entries.is_empty() {
+            parent.spawn(TextBundle::from_section(
+                "Complete a level to add it here",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    color: SMALL_TEXT_COLOR,
+                },
+            ));
+            return;
+        }
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(80.0)),
+                    flex_direction: FlexDirection::Column,
+                    overflow: Overflow::Hidden,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            flex_shrink: 0.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(GalleryList::default())
+                    .with_children(|parent| {
+                        for (index, entry) in gallery.entries.iter().enumerate().rev() {
+                            spawn_entry(parent, index, entry, &font, &mut images);
+                        }
+                    });
+            });
+    });
+}
+
+fn spawn_entry(
+    parent: &mut ChildBuilder,
+    index: usize,
+    entry: &GalleryEntry,
+    font: &Handle<Font>,
+    images: &mut Assets<Image>,
+) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(WINDOW_WIDTH * 0.9), Val::Px(THUMBNAIL_SIZE)),
+                margin: UiRect::all(Val::Px(5.0)),
+                align_items: AlignItems::Center,
+                flex_shrink: 0.0,
+                ..Default::default()
+            },
+            background_color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(GalleryEntryButton {
+            index,
+            pressed_at: None,
+        })
+        .with_children(|parent| {
+            match string_to_image(&entry.file.svg, THUMBNAIL_SIZE) {
+                Ok(image) => {
+                    let size = image.size();
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(size.x), Val::Px(size.y)),
+                            margin: UiRect::horizontal(Val::Px((THUMBNAIL_SIZE - size.x) * 0.5)),
+                            ..Default::default()
+                        },
+                        image: images.add(image).into(),
+                        ..Default::default()
+                    });
+                }
+                Err(err) => {
+                    warn!("Could not create thumbnail: {}", err);
+                    parent.spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(THUMBNAIL_SIZE), Val::Px(THUMBNAIL_SIZE)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
+                }
+            }
+
+            parent.spawn(
+                TextBundle::from_section(
+                    format!("{}\n{}\n{} shapes", entry.mode, entry.date, entry.shapes),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 16.0,
+                        color: SMALL_TEXT_COLOR,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::left(Val::Px(10.0)),
+                    ..Default::default()
+                }),
+            );
+        });
+}
+
+fn scroll_gallery(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    touches: Res<Touches>,
+    mut lists: Query<(&mut GalleryList, &mut Style, &Node, &Parent)>,
+    nodes: Query<&Node>,
+) {
+    let mut delta: f32 = mouse_wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    delta += touches.iter().map(|touch| touch.delta().y).sum::<f32>();
+
+    if delta == 0.0 {
+        return;
+    }
+
+    for (mut list, mut style, node, parent) in lists.iter_mut() {
+        let visible_height = nodes.get(parent.get()).map(|x| x.size().y).unwrap_or_default();
+        let max_scroll = (node.size().y - visible_height).max(0.0);
+
+        list.position = (list.position + delta).clamp(-max_scroll, 0.0);
+        style.position.top = Val::Px(list.position);
+    }
+}
+
+fn gallery_button_system(
+    mut buttons: Query<(&Interaction, &mut GalleryEntryButton), Changed<Interaction>>,
+    lists: Query<&GalleryList>,
+    pkv: Res<PkvStore>,
+) {
+    let Ok(list) = lists.get_single() else {return;};
+
+    for (interaction, mut button) in buttons.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            button.pressed_at = Some(list.position);
+        } else if let Some(pressed_at) = button.pressed_at.take() {
+            if (list.position - pressed_at).abs() < TAP_DISTANCE {
+                if let Some(entry) = Gallery::load(&pkv).entries.get(button.index) {
+                    download_png(&entry.file);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(shapes: usize) -> serde_json::Value {
+        serde_json::json!({
+            "file": {"title": format!("Equilibrium Infinite {shapes}"), "svg": "<svg></svg>"},
+            "date": "2023-01-01",
+            "mode": "Infinite",
+            "shapes": shapes
+        })
+    }
+
+    fn load(entries: Vec<serde_json::Value>) -> Gallery {
+        Gallery::from_versioned_json(&serde_json::json!({ "V1": { "entries": entries } })).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let gallery = load(vec![entry(3), entry(4)]);
+
+        assert_eq!(gallery.entries.len(), 2);
+        assert_eq!(Gallery::from_versioned_json(&gallery.to_versioned_json()), Some(gallery));
+    }
+
+    #[test]
+    fn test_merge() {
+        let merged = load(vec![entry(3), entry(4)]).merge(load(vec![entry(4), entry(5)]));
+
+        assert_eq!(merged, load(vec![entry(3), entry(4), entry(5)]));
+    }
+
+    #[test]
+    fn test_merge_keeps_newest_entries() {
+        let entries = |range: std::ops::Range<usize>| range.map(entry).collect();
+        let merged = load(entries(0..MAX_GALLERY_ENTRIES)).merge(load(entries(100..110)));
+
+        assert_eq!(merged.entries.len(), MAX_GALLERY_ENTRIES);
+        assert_eq!(merged.entries[0].shapes, 10);
+        assert_eq!(merged.entries.last().unwrap().shapes, 109);
+    }
+
+    #[test]
+    fn test_unknown_version_is_not_loaded() {
+        let newer = serde_json::json!({ "V999": { "entries": [] } });
+        let unversioned = serde_json::json!({ "entries": [] });
+
+        assert_eq!(Gallery::try_load(&newer), None);
+        assert_eq!(Gallery::try_load(&unversioned), None);
+        assert_eq!(
+            Gallery::try_load(&serde_json::Value::Null),
+            Some(Gallery::default())
+        );
+    }
+}
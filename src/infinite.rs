@@ -0,0 +1,85 @@
+use serde::*;
+
+use crate::shape_maker::ShapeIndex;
+use crate::*;
+
+pub struct InfinitePlugin;
+
+impl Plugin for InfinitePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(save_infinite_layout);
+    }
+}
+
+/// The number of shapes in the first infinite level
+pub const INFINITE_START_SHAPES: usize = 5;
+
+/// Seconds to wait after a drag ends before saving, so the shapes can settle
+const LAYOUT_SAVE_DELAY: f64 = 2.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InfiniteProgress {
+    pub shapes: usize,
+    pub seed: u64,
+    /// The shapes as they were last left, if the player has moved any
+    #[serde(default)]
+    pub layout: Option<Vec<SavedShape>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedShape {
+    pub shape_index: usize,
+    pub position: (f32, f32),
+    pub angle: f32,
+    pub locked: bool,
+}
+
+impl SavedShape {
+    pub fn new(shape_index: usize, transform: &Transform, draggable: &Draggable) -> Self {
+        Self {
+            shape_index,
+            position: (transform.translation.x, transform.translation.y),
+            angle: transform.rotation.to_euler(EulerRot::ZYX).0,
+            locked: draggable.is_locked(),
+        }
+    }
+}
+
+fn save_infinite_layout(
+    mut drag_ended_events: EventReader<DragEndedEvent>,
+    time: Res<Time>,
+    mut save_at: Local<Option<f64>>,
+    current_level: Res<CurrentLevel>,
+    shapes: Query<(&ShapeIndex, &Transform, &Draggable)>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    let LevelType::Infinite { seed } = current_level.0.level_type else {
+        *save_at = None;
+        return;
+    };
+
+    let now = time.elapsed_seconds_f64();
+    if drag_ended_events.iter().last().is_some() {
+        *save_at = Some(now + LAYOUT_SAVE_DELAY);
+    }
+
+    let Some(time_to_save) = *save_at else {return;};
+    if now < time_to_save {
+        return;
+    }
+    *save_at = None;
+
+    let layout: Vec<SavedShape> = shapes
+        .iter()
+        .map(|(index, transform, draggable)| SavedShape::new(index.0, transform, draggable))
+        .collect();
+
+    SavedData::update(&mut pkv, |mut x| {
+        if let Some(progress) = x.infinite.as_mut() {
+            if progress.seed == seed && progress.shapes == current_level.0.shapes {
+                progress.layout = Some(layout);
+            }
+        }
+        x
+    });
+}
@@ -3,6 +3,7 @@ use bevy::input::mouse::*;
 use bevy::input::touch::*;
 use bevy::prelude::*;
 use bevy::render::camera::RenderTarget;
+use bevy::utils::HashMap;
 
 use crate::*;
 
@@ -10,10 +11,20 @@ pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(InputDetector::default())
-            .add_system(touch_listener)
-            .add_system(keyboard_listener)
-            .add_system(mousewheel_listener)
-            .add_system(mousebutton_listener.after(touch_listener));
+            .init_resource::<GamepadCursors>()
+            .init_resource::<KeyboardSelection>()
+            .add_system(touch_listener.with_run_criteria(not_watching_replay))
+            .add_system(keyboard_listener.with_run_criteria(not_watching_replay))
+            .add_system(mousewheel_listener.with_run_criteria(not_watching_replay))
+            .add_system(
+                mousebutton_listener
+                    .with_run_criteria(not_watching_replay)
+                    .after(touch_listener),
+            )
+            .add_system(update_cursor_icon.after(draggable::drag_start))
+            .add_system(gamepad_listener.with_run_criteria(not_watching_replay))
+            .add_system(draw_gamepad_cursors.after(gamepad_listener))
+            .add_system(draw_keyboard_selection.after(keyboard_listener));
     }
 }
 
@@ -38,6 +49,7 @@ pub fn mousebutton_listener(
             ew_drag_start.send(DragStartEvent {
                 drag_source: DragSource::Mouse,
                 position,
+                target: None,
             });
         }
     } else if mouse_button_input.pressed(MouseButton::Left) {
@@ -51,6 +63,44 @@ pub fn mousebutton_listener(
     }
 }
 
+/// Show what clicking would do to the shape under the mouse
+fn update_cursor_icon(
+    mut windows: ResMut<Windows>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    rapier_context: Res<RapierContext>,
+    draggables: Query<&Draggable>,
+) {
+    let Some(window) = windows.get_primary_mut() else {return;};
+    let Some(screen_pos) = window.cursor_position() else {return;};
+
+    let icon = if draggables
+        .iter()
+        .any(|x| x.has_drag_source(DragSource::Mouse))
+    {
+        CursorIcon::Grabbing
+    } else {
+        let (camera, camera_transform) = q_camera.single();
+        let position =
+            convert_screen_to_world_position(screen_pos, window, camera, camera_transform);
+        let mut icon = CursorIcon::Default;
+
+        rapier_context.intersections_with_point(position, default(), |entity| {
+            match draggables.get(entity) {
+                Ok(Draggable::Free) => icon = CursorIcon::Grab,
+                // Locked shapes can still be picked up, so this isn't `NotAllowed`
+                Ok(Draggable::Locked) => icon = CursorIcon::Move,
+                _ => return true, //keep looking for intersections
+            }
+            false
+        });
+        icon
+    };
+
+    if window.cursor_icon() != icon {
+        window.set_cursor_icon(icon);
+    }
+}
+
 pub fn get_cursor_position(
     // need to get window dimensions
     windows: Res<Windows>,
@@ -113,6 +163,7 @@ pub fn touch_listener(
                 ew_drag_start.send(DragStartEvent {
                     drag_source: DragSource::Touch { touch_id: ev.id },
                     position: ev.position,
+                    target: None,
                 });
                 debug!("Touch {} started at: {:?}", ev.id, ev.position);
             }
@@ -139,45 +190,335 @@ pub fn touch_listener(
     }
 }
 
-const SNAP_RESOLUTION: f32 = std::f32::consts::TAU / 16.0;
+/// How far the arrow keys move a shape, in pixels
+const KEYBOARD_FINE_STEP: f32 = 4.0;
+/// How far the arrow keys move a shape while shift is held
+const KEYBOARD_COARSE_STEP: f32 = 32.0;
+const SELECTION_RADIUS: f32 = crate::shape_maker::SHAPE_SIZE * 0.75;
 
+/// The shape chosen with Tab
+#[derive(Resource, Default)]
+pub struct KeyboardSelection {
+    pub entity: Option<Entity>,
+    /// Where the selected shape is being moved to
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+struct SelectionHighlight;
+
+/// Handles the actions bound in `KeyBindings`, and the arrow keys which move a shape picked up with the keyboard.
+/// Holding shift makes `SelectNext` go backwards and the arrow keys move further.
 pub fn keyboard_listener(
     mut key_evr: EventReader<KeyboardInput>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    settings: Res<Settings>,
+    play_area: Res<PlayArea>,
+    mut selection: ResMut<KeyboardSelection>,
+    draggables: Query<(Entity, &Draggable, &Transform)>,
+    mut menu_query: Query<&mut Visibility, With<MainMenu>>,
     mut rotate_evw: EventWriter<RotateEvent>,
+    mut undo_evw: EventWriter<UndoEvent>,
+    mut change_level_evw: EventWriter<ChangeLevelEvent>,
+    mut ew_drag_start: EventWriter<DragStartEvent>,
+    mut ew_drag_move: EventWriter<DragMoveEvent>,
+    mut ew_drag_end: EventWriter<DragEndEvent>,
 ) {
-    for ev in key_evr.iter() {
-        if let Some(code) = ev.key_code {
-            if let bevy::input::ButtonState::Pressed = ev.state {
-                let angle = match code {
-                    KeyCode::E => Some(-SNAP_RESOLUTION),
-                    KeyCode::Q => Some(SNAP_RESOLUTION),
-                    _ => None,
-                };
-                if let Some(angle) = angle {
-                    rotate_evw.send(RotateEvent {
-                        angle,
-                        snap_resolution: Some(SNAP_RESOLUTION),
+    let pressed_keys: Vec<KeyCode> = key_evr
+        .iter()
+        .filter(|ev| ev.state == bevy::input::ButtonState::Pressed)
+        .filter_map(|ev| ev.key_code)
+        .collect();
+    if rebinding.0.is_some() || rebinding.is_changed() {
+        return; // The key is being bound to an action
+    }
+
+    let drag_source = DragSource::Keyboard;
+    if let Some(entity) = selection.entity {
+        if !draggables.contains(entity) {
+            selection.entity = None; // The shape was removed when the level changed
+        }
+    }
+    let mut is_dragging = draggables
+        .iter()
+        .any(|x| x.1.has_drag_source(drag_source));
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+
+    let actions = pressed_keys
+        .iter()
+        .map(|code| Binding::Key(*code))
+        .chain(mouse_buttons.get_just_pressed().map(|x| Binding::Mouse(*x)))
+        .filter_map(|binding| bindings.action_for(binding));
+
+    for action in actions {
+        match action {
+            InputAction::Undo => undo_evw.send(UndoEvent),
+            InputAction::Reset => change_level_evw.send(ChangeLevelEvent::ResetLevel),
+            InputAction::ToggleMenu => {
+                for mut visibility in menu_query.iter_mut() {
+                    visibility.is_visible = !visibility.is_visible;
+                }
+            }
+            InputAction::SelectNext => {
+                if !is_dragging {
+                    select_next(&mut selection, &draggables, shift);
+                }
+            }
+            InputAction::PickUp => {
+                if is_dragging {
+                    ew_drag_end.send(DragEndEvent { drag_source });
+                    is_dragging = false;
+                } else if let Some((entity, _, transform)) =
+                    selection.entity.and_then(|e| draggables.get(e).ok())
+                {
+                    selection.position = transform.translation.truncate();
+                    // The centre of a shape may not be inside it, so pick up the shape itself
+                    ew_drag_start.send(DragStartEvent {
+                        drag_source,
+                        position: selection.position,
+                        target: Some(entity),
                     });
+                    is_dragging = true;
                 }
             }
+            InputAction::RotateClockwise | InputAction::RotateAnticlockwise => {
+                let snap_resolution = settings.snap_resolution();
+                let angle = if action == InputAction::RotateClockwise {
+                    -snap_resolution
+                } else {
+                    snap_resolution
+                };
+                rotate_evw.send(RotateEvent {
+                    angle,
+                    snap_resolution: Some(snap_resolution),
+                });
+            }
         }
     }
+
+    if !is_dragging {
+        return;
+    }
+    let step = if shift {
+        KEYBOARD_COARSE_STEP
+    } else {
+        KEYBOARD_FINE_STEP
+    };
+    for code in pressed_keys {
+        let direction = match code {
+            KeyCode::Left => Vec2::NEG_X,
+            KeyCode::Right => Vec2::X,
+            KeyCode::Up => Vec2::Y,
+            KeyCode::Down => Vec2::NEG_Y,
+            _ => continue,
+        };
+        selection.position = play_area.clamp(selection.position + direction * step);
+        ew_drag_move.send(DragMoveEvent {
+            drag_source,
+            new_position: selection.position,
+        });
+    }
 }
 
 pub fn mousewheel_listener(
     mut scroll_evr: EventReader<MouseWheel>,
+    bindings: Res<KeyBindings>,
+    settings: Res<Settings>,
     mut ev_rotate: EventWriter<RotateEvent>,
 ) {
+    let direction = if bindings.wheel_inverted { -1.0 } else { 1.0 };
+    let snap_resolution = settings.snap_resolution();
     for ev in scroll_evr.iter() {
-        let angle = (ev.x + ev.y).signum() * SNAP_RESOLUTION;
+        let angle = (ev.x + ev.y).signum() * direction * snap_resolution;
         let event = RotateEvent {
             angle,
-            snap_resolution: Some(SNAP_RESOLUTION),
+            snap_resolution: Some(snap_resolution),
         };
         ev_rotate.send(event);
     }
 }
 
+/// How fast the left stick moves the gamepad cursor, in pixels per second
+const GAMEPAD_CURSOR_SPEED: f32 = 300.0;
+const GAMEPAD_CURSOR_RADIUS: f32 = 8.0;
+
+/// The position of the virtual cursor of each connected gamepad
+#[derive(Resource, Default)]
+pub struct GamepadCursors(HashMap<usize, Vec2>);
+
+#[derive(Component)]
+struct GamepadCursorMarker {
+    id: usize,
+}
+
+/// The left stick moves the cursor, the south button picks up and puts down shapes,
+/// the east button puts down shapes and the shoulder buttons rotate.
+pub fn gamepad_listener(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<Input<GamepadButton>>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    play_area: Res<PlayArea>,
+    mut cursors: ResMut<GamepadCursors>,
+    draggables: Query<&Draggable>,
+    mut ew_drag_start: EventWriter<DragStartEvent>,
+    mut ew_drag_move: EventWriter<DragMoveEvent>,
+    mut ew_drag_end: EventWriter<DragEndEvent>,
+    mut rotate_evw: EventWriter<RotateEvent>,
+) {
+    cursors
+        .0
+        .retain(|id, _| gamepads.iter().any(|gamepad| gamepad.id == *id));
+
+    for gamepad in gamepads.iter() {
+        let drag_source = DragSource::Gamepad { id: gamepad.id };
+        let position = cursors.0.entry(gamepad.id).or_default();
+        let is_dragging = draggables.iter().any(|x| x.has_drag_source(drag_source));
+
+        let stick = Vec2::new(
+            axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or_default(),
+            axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or_default(),
+        );
+        if stick != Vec2::ZERO {
+            *position =
+                play_area.clamp(*position + stick * GAMEPAD_CURSOR_SPEED * time.delta_seconds());
+
+            if is_dragging {
+                ew_drag_move.send(DragMoveEvent {
+                    drag_source,
+                    new_position: *position,
+                });
+            }
+        }
+
+        let just_pressed =
+            |button_type| buttons.just_pressed(GamepadButton::new(gamepad, button_type));
+
+        if !is_dragging && just_pressed(GamepadButtonType::South) {
+            ew_drag_start.send(DragStartEvent {
+                drag_source,
+                position: *position,
+                target: None,
+            });
+        } else if is_dragging
+            && (just_pressed(GamepadButtonType::South) || just_pressed(GamepadButtonType::East))
+        {
+            ew_drag_end.send(DragEndEvent { drag_source });
+        }
+
+        let snap_resolution = settings.snap_resolution();
+        let angle = if just_pressed(GamepadButtonType::LeftTrigger) {
+            Some(snap_resolution)
+        } else if just_pressed(GamepadButtonType::RightTrigger) {
+            Some(-snap_resolution)
+        } else {
+            None
+        };
+        if let Some(angle) = angle {
+            rotate_evw.send(RotateEvent {
+                angle,
+                snap_resolution: Some(snap_resolution),
+            });
+        }
+    }
+}
+
+/// Select the next shape from left to right, or right to left if `reverse`
+fn select_next(
+    selection: &mut KeyboardSelection,
+    draggables: &Query<(Entity, &Draggable, &Transform)>,
+    reverse: bool,
+) {
+    let mut shapes: Vec<(Entity, f32)> = draggables
+        .iter()
+        .filter(|x| !x.1.is_dragged())
+        .map(|(entity, _, transform)| (entity, transform.translation.x))
+        .collect();
+    if shapes.is_empty() {
+        selection.entity = None;
+        return;
+    }
+    shapes.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    let current = selection
+        .entity
+        .and_then(|e| shapes.iter().position(|x| x.0 == e));
+    let index = match (current, reverse) {
+        (None, false) => 0,
+        (None, true) => shapes.len() - 1,
+        (Some(i), false) => (i + 1) % shapes.len(),
+        (Some(i), true) => (i + shapes.len() - 1) % shapes.len(),
+    };
+    selection.entity = Some(shapes[index].0);
+}
+
+fn draw_keyboard_selection(
+    mut commands: Commands,
+    selection: Res<KeyboardSelection>,
+    transforms: Query<&Transform, (With<Draggable>, Without<SelectionHighlight>)>,
+    mut highlights: Query<(Entity, &mut Transform), With<SelectionHighlight>>,
+) {
+    let position = selection
+        .entity
+        .and_then(|e| transforms.get(e).ok())
+        .map(|x| x.translation.truncate());
+
+    match (position, highlights.get_single_mut()) {
+        (Some(position), Ok((_, mut transform))) => {
+            transform.translation = position.extend(transform.translation.z);
+        }
+        (Some(position), Err(_)) => {
+            commands
+                .spawn(GeometryBuilder::build_as(
+                    &shapes::Circle {
+                        radius: SELECTION_RADIUS,
+                        center: Vec2::ZERO,
+                    },
+                    DrawMode::Stroke(StrokeMode::new(Color::BLACK, 2.0)),
+                    Transform::from_translation(position.extend(10.0)),
+                ))
+                .insert(SelectionHighlight);
+        }
+        (None, Ok((entity, _))) => commands.entity(entity).despawn(),
+        (None, Err(_)) => {}
+    }
+}
+
+fn draw_gamepad_cursors(
+    mut commands: Commands,
+    cursors: Res<GamepadCursors>,
+    mut markers: Query<(Entity, &GamepadCursorMarker, &mut Transform)>,
+) {
+    for (entity, marker, mut transform) in markers.iter_mut() {
+        match cursors.0.get(&marker.id) {
+            Some(position) => transform.translation = position.extend(transform.translation.z),
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    for (id, position) in cursors.0.iter() {
+        if markers.iter().any(|x| x.1.id == *id) {
+            continue;
+        }
+        commands
+            .spawn(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius: GAMEPAD_CURSOR_RADIUS,
+                    center: Vec2::ZERO,
+                },
+                DrawMode::Stroke(StrokeMode::new(Color::BLACK, 2.0)),
+                Transform::from_translation(position.extend(10.0)),
+            ))
+            .insert(GamepadCursorMarker { id: *id });
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct InputDetector {
     pub is_touch: bool,
@@ -0,0 +1,130 @@
+use bevy_rapier2d::rapier::dynamics::{JointAxesMask, JointAxis};
+use serde::*;
+
+use crate::*;
+
+pub struct JointsPlugin;
+
+impl Plugin for JointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, draw_joints);
+    }
+}
+
+const JOINT_COLOR: Color = Color::DARK_GRAY;
+const JOINT_WIDTH: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum JointKind {
+    /// The shapes can turn about the point between them
+    Revolute,
+    /// The shapes are held together as though they were one shape
+    Fixed,
+    /// The centres of the shapes can be at most `length` apart along each axis.
+    /// Rapier does not have a distance joint yet, so the slack is square rather than round.
+    Rope { length: f32 },
+}
+
+impl JointKind {
+    /// A joint which holds the shapes as they are now
+    pub fn build(&self, parent: &Transform, child: &Transform) -> GenericJoint {
+        let midpoint = (parent.translation + child.translation).truncate() * 0.5;
+
+        match self {
+            JointKind::Revolute => RevoluteJointBuilder::new()
+                .local_anchor1(local_point(parent, midpoint))
+                .local_anchor2(local_point(child, midpoint))
+                .into(),
+            JointKind::Fixed => FixedJointBuilder::new()
+                .local_anchor1(local_point(parent, midpoint))
+                .local_anchor2(local_point(child, midpoint))
+                .local_basis1(z_angle(child) - z_angle(parent))
+                .into(),
+            JointKind::Rope { length } => GenericJointBuilder::new(JointAxesMask::empty())
+                .limits(JointAxis::X, [-length, *length])
+                .limits(JointAxis::Y, [-length, *length])
+                .into(),
+        }
+    }
+}
+
+fn local_point(transform: &Transform, point: Vec2) -> Vec2 {
+    (transform.rotation.inverse() * (point.extend(0.0) - transform.translation)).truncate()
+}
+
+fn z_angle(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::ZYX).0
+}
+
+/// The line drawn between the shapes of a joint
+#[derive(Component)]
+pub struct JointConnector {
+    /// The shape with the `ImpulseJoint`
+    child: Entity,
+}
+
+/// Join two shapes where they are now and draw a connector between them
+pub fn create_joint(
+    commands: &mut Commands,
+    kind: JointKind,
+    (parent, parent_transform): (Entity, &Transform),
+    (child, child_transform): (Entity, &Transform),
+) {
+    commands.entity(child).insert(ImpulseJoint::new(
+        parent,
+        kind.build(parent_transform, child_transform),
+    ));
+
+    commands
+        .spawn(GeometryBuilder::build_as(
+            &shapes::Line(Vec2::ZERO, Vec2::ZERO),
+            DrawMode::Stroke(StrokeMode::new(JOINT_COLOR, JOINT_WIDTH)),
+            Transform::from_translation(Vec3::Z * 2.0),
+        ))
+        .insert(JointConnector { child });
+}
+
+/// Draw each connector from the centre of one shape, through the joint anchors, to the centre of the other
+fn draw_joints(
+    mut commands: Commands,
+    mut connectors: Query<(Entity, &JointConnector, &mut Path)>,
+    joints: Query<(&ImpulseJoint, &Transform)>,
+    transforms: Query<&Transform>,
+) {
+    for (entity, connector, mut path) in connectors.iter_mut() {
+        let Ok((joint, child_transform)) = joints.get(connector.child) else {
+            commands.entity(entity).despawn(); // The shapes were removed when the level changed
+            continue;
+        };
+        let Ok(parent_transform) = transforms.get(joint.parent) else {continue;};
+
+        let anchor1 = parent_transform.transform_point(joint.data.local_anchor1().extend(0.0));
+        let anchor2 = child_transform.transform_point(joint.data.local_anchor2().extend(0.0));
+
+        *path = ShapePath::build_as(&shapes::Polygon {
+            points: vec![
+                parent_transform.translation.truncate(),
+                anchor1.truncate(),
+                anchor2.truncate(),
+                child_transform.translation.truncate(),
+            ],
+            closed: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_point() {
+        let transform = Transform {
+            translation: Vec3::new(10.0, 0.0, 0.0),
+            rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            scale: Vec3::ONE,
+        };
+        let local = local_point(&transform, Vec2::new(10.0, 5.0));
+        assert!(local.abs_diff_eq(Vec2::new(5.0, 0.0), 0.0001), "{local}");
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+
+use bevy::input::keyboard::KeyboardInput;
+use serde::*;
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::*;
+
+pub struct KeyBindingsPlugin;
+
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .init_resource::<Rebinding>()
+            .add_event::<ShowControlsEvent>()
+            .add_startup_system(load_key_bindings)
+            .add_system(show_controls)
+            .add_system(binding_button_system)
+            .add_system(capture_binding.before(input::keyboard_listener))
+            .add_system(update_binding_texts.after(binding_button_system));
+    }
+}
+
+const KEY_BINDINGS_KEY: &str = "key_bindings";
+
+pub struct ShowControlsEvent;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, Serialize, Deserialize,
+)]
+pub enum InputAction {
+    RotateClockwise,
+    RotateAnticlockwise,
+    Undo,
+    Reset,
+    ToggleMenu,
+    SelectNext,
+    PickUp,
+}
+
+impl InputAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::RotateClockwise => "Rotate clockwise",
+            InputAction::RotateAnticlockwise => "Rotate anticlockwise",
+            InputAction::Undo => "Undo",
+            InputAction::Reset => "Reset level",
+            InputAction::ToggleMenu => "Menu",
+            InputAction::SelectNext => "Select shape",
+            InputAction::PickUp => "Pick up / drop",
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        match self {
+            InputAction::RotateClockwise => vec![Binding::Key(KeyCode::E)],
+            InputAction::RotateAnticlockwise => vec![Binding::Key(KeyCode::Q)],
+            InputAction::Undo => vec![Binding::Key(KeyCode::Z)],
+            InputAction::Reset => vec![Binding::Key(KeyCode::R)],
+            InputAction::ToggleMenu => vec![Binding::Key(KeyCode::Escape)],
+            InputAction::SelectNext => vec![Binding::Key(KeyCode::Tab)],
+            InputAction::PickUp => vec![Binding::Key(KeyCode::Space), Binding::Key(KeyCode::Return)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    /// The left mouse button is always used for dragging
+    Mouse(MouseButton),
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub actions: BTreeMap<InputAction, Vec<Binding>>,
+    /// Reverse the direction the mouse wheel rotates shapes
+    pub wheel_inverted: bool,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            actions: InputAction::iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+            wheel_inverted: false,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Load the saved bindings. Actions added since they were saved get their default bindings.
+    pub fn load(pkv: &PkvStore) -> Self {
+        let mut bindings = pkv.get::<Self>(KEY_BINDINGS_KEY).unwrap_or_default();
+        bindings.add_missing_actions();
+        bindings
+    }
+
+    fn add_missing_actions(&mut self) {
+        for action in InputAction::iter() {
+            self.actions
+                .entry(action)
+                .or_insert_with(|| action.default_bindings());
+        }
+    }
+
+    fn save(&self, pkv: &mut PkvStore) {
+        if let Err(err) = pkv.set(KEY_BINDINGS_KEY, self) {
+            error!("Could not save key bindings: {}", err);
+        }
+    }
+
+    pub fn action_for(&self, binding: Binding) -> Option<InputAction> {
+        self.actions
+            .iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn description(&self, action: InputAction) -> String {
+        match self.actions.get(&action) {
+            Some(bindings) if !bindings.is_empty() => bindings
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(" / "),
+            _ => "None".to_string(),
+        }
+    }
+
+    /// Bind an action to just this, removing it from any other action
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) {
+        for bindings in self.actions.values_mut() {
+            bindings.retain(|x| *x != binding);
+        }
+        self.actions.insert(action, vec![binding]);
+    }
+}
+
+/// The action waiting for the player to press its new key
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<InputAction>);
+
+#[derive(Component, Clone, Copy, PartialEq)]
+enum BindingRow {
+    Action(InputAction),
+    WheelDirection,
+    ResetDefaults,
+}
+
+impl BindingRow {
+    fn text(&self, bindings: &KeyBindings, rebinding: &Rebinding) -> String {
+        match self {
+            BindingRow::Action(action) if rebinding.0 == Some(*action) => {
+                format!("{}: press a key", action.name())
+            }
+            BindingRow::Action(action) => {
+                format!("{}: {}", action.name(), bindings.description(*action))
+            }
+            BindingRow::WheelDirection => format!(
+                "Mouse wheel: {}",
+                if bindings.wheel_inverted {
+                    "Inverted"
+                } else {
+                    "Normal"
+                }
+            ),
+            BindingRow::ResetDefaults => "Reset to defaults".to_string(),
+        }
+    }
+}
+
+/// Marks the text of a row on the controls screen
+#[derive(Component)]
+struct BindingText(BindingRow);
+
+fn load_key_bindings(mut bindings: ResMut<KeyBindings>, pkv: Res<PkvStore>) {
+    *bindings = KeyBindings::load(&pkv);
+}
+
+fn show_controls(
+    mut commands: Commands,
+    mut events: EventReader<ShowControlsEvent>,
+    bindings: Res<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    rebinding.0 = None;
+
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let rows = InputAction::iter()
+        .map(BindingRow::Action)
+        .chain([BindingRow::WheelDirection, BindingRow::ResetDefaults]);
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Controls",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        for row in rows {
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(4.0)),
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..Default::default()
+                })
+                .insert(row)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            row.text(&bindings, &rebinding),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 16.0,
+                                color: SMALL_TEXT_COLOR,
+                            },
+                        ))
+                        .insert(BindingText(row));
+                });
+        }
+    });
+}
+
+fn binding_button_system(
+    rows: Query<(&Interaction, &BindingRow), Changed<Interaction>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match row {
+            BindingRow::Action(action) => rebinding.0 = Some(*action),
+            BindingRow::WheelDirection => {
+                bindings.wheel_inverted = !bindings.wheel_inverted;
+                bindings.save(&mut pkv);
+            }
+            BindingRow::ResetDefaults => {
+                *bindings = KeyBindings::default();
+                rebinding.0 = None;
+                bindings.save(&mut pkv);
+            }
+        }
+    }
+}
+
+/// Give the action being rebound the next key or mouse button pressed
+fn capture_binding(
+    mut key_evr: EventReader<KeyboardInput>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    rows: Query<&BindingRow>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    if rows.is_empty() {
+        rebinding.0 = None; // The controls screen was closed
+        return;
+    }
+
+    let key = key_evr
+        .iter()
+        .filter(|x| x.state == bevy::input::ButtonState::Pressed)
+        .find_map(|x| x.key_code)
+        .map(Binding::Key);
+    let mouse = mouse_buttons
+        .get_just_pressed()
+        .find(|x| **x != MouseButton::Left)
+        .map(|x| Binding::Mouse(*x));
+
+    if let Some(binding) = key.or(mouse) {
+        bindings.rebind(action, binding);
+        bindings.save(&mut pkv);
+        rebinding.0 = None;
+    }
+}
+
+fn update_binding_texts(
+    bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    mut texts: Query<(&mut Text, &BindingText)>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (mut text, row) in texts.iter_mut() {
+        text.sections[0].value = row.0.text(&bindings, &rebinding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebind_moves_binding() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.action_for(Binding::Key(KeyCode::Q)),
+            Some(InputAction::RotateAnticlockwise)
+        );
+
+        bindings.rebind(InputAction::Undo, Binding::Key(KeyCode::Q));
+
+        assert_eq!(
+            bindings.action_for(Binding::Key(KeyCode::Q)),
+            Some(InputAction::Undo)
+        );
+        assert_eq!(bindings.action_for(Binding::Key(KeyCode::Z)), None);
+        assert_eq!(bindings.description(InputAction::RotateAnticlockwise), "None");
+    }
+
+    #[test]
+    fn test_missing_actions_get_defaults() {
+        let mut bindings: KeyBindings =
+            serde_json::from_str(r#"{"actions":{"Undo":[{"Key":"U"}]}}"#).unwrap();
+        bindings.add_missing_actions();
+
+        assert_eq!(
+            bindings.action_for(Binding::Key(KeyCode::U)),
+            Some(InputAction::Undo)
+        );
+        assert_eq!(
+            bindings.action_for(Binding::Key(KeyCode::E)),
+            Some(InputAction::RotateClockwise)
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::time::Duration;
 use crate::*;
 use bevy_tweening::lens::*;
 use bevy_tweening::*;
+use serde::{Deserialize, Serialize};
 
 pub const SMALL_TEXT_COLOR: Color = Color::DARK_GRAY;
 
@@ -12,6 +13,7 @@ pub struct LevelPlugin;
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentLevel>()
+            .init_resource::<Campaign>()
             .add_startup_system(setup_level_ui)
             .add_startup_system_to_stage(StartupStage::PostStartup, skip_tutorial)
             .add_event::<ChangeLevelEvent>();
@@ -27,6 +29,9 @@ pub fn handle_change_level(
     level_ui: Query<Entity, With<LevelUI>>,
     asset_server: Res<AssetServer>,
     mut pkv: ResMut<PkvStore>,
+    campaign: Res<Campaign>,
+    bindings: Res<KeyBindings>,
+    settings: Res<Settings>,
 ) {
     if let Some(event) = change_level_events.iter().next() {
         for (e, _) in draggables.iter() {
@@ -34,7 +39,17 @@ pub fn handle_change_level(
             commands.entity(e).despawn();
         }
 
-        current_level.0 = event.apply(&current_level.0, &mut pkv);
+        current_level.0 = event.apply(&current_level.0, &mut pkv, &campaign);
+
+        let saved_layout = match (event, current_level.0.level_type) {
+            (ChangeLevelEvent::StartInfinite, LevelType::Infinite { .. }) => {
+                SavedData::get_or_create(&mut pkv)
+                    .infinite
+                    .and_then(|x| x.layout)
+            }
+            (ChangeLevelEvent::StartReplay { layout, .. }, _) => Some(layout.clone()),
+            _ => None,
+        };
 
         level::start_level(
             commands,
@@ -42,6 +57,10 @@ pub fn handle_change_level(
             level_ui,
             input_detector,
             asset_server,
+            &campaign,
+            &bindings,
+            &settings,
+            saved_layout,
         );
     }
 }
@@ -71,32 +90,43 @@ fn start_level(
     level_ui: Query<Entity, With<LevelUI>>,
     input_detector: Res<InputDetector>,
     asset_server: Res<AssetServer>,
+    campaign: &Campaign,
+    bindings: &KeyBindings,
+    settings: &Settings,
+    saved_layout: Option<Vec<SavedShape>>,
 ) {
     if let Some(level_ui_entity) = level_ui.iter().next() {
         let mut builder = commands.entity(level_ui_entity);
         builder.despawn_descendants();
 
-        if let Some(text) = level.get_text(input_detector) {
+        if let Some(text) = level.get_text(input_detector, campaign, bindings) {
             builder.with_children(|parent| {
                 const LEVEL_TEXT_SECONDS: u64 = 20;
-                parent
-                    .spawn(
-                        TextBundle::from_section(
-                            text,
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                                font_size: 20.0,
-                                color: SMALL_TEXT_COLOR,
-                            },
-                        )
-                        .with_text_alignment(TextAlignment::CENTER)
-                        .with_style(Style {
-                            align_self: AlignSelf::Center,
-                            ..Default::default()
-                        }),
+                let mut text_entity = parent.spawn(
+                    TextBundle::from_section(
+                        text,
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: SMALL_TEXT_COLOR,
+                        },
                     )
-                    .insert(Animator::new(Tween::new(
-                        EaseFunction::QuadraticInOut,
+                    .with_text_alignment(TextAlignment::CENTER)
+                    .with_style(Style {
+                        align_self: AlignSelf::Center,
+                        ..Default::default()
+                    }),
+                );
+
+                if settings.fade_text {
+                    // With reduced motion the text disappears at the end instead of fading
+                    let ease_method: EaseMethod = if settings.reduced_motion {
+                        EaseMethod::Discrete(0.99)
+                    } else {
+                        EaseFunction::QuadraticInOut.into()
+                    };
+                    text_entity.insert(Animator::new(Tween::new(
+                        ease_method,
                         Duration::from_secs(LEVEL_TEXT_SECONDS),
                         TextColorLens {
                             section: 0,
@@ -104,6 +134,7 @@ fn start_level(
                             end: Color::NONE,
                         },
                     )));
+                }
             });
         }
 
@@ -130,7 +161,7 @@ fn start_level(
         }
     }
 
-    shape_maker::create_level_shapes(&mut commands, level);
+    shape_maker::create_level_shapes(&mut commands, level, campaign, saved_layout);
 }
 
 pub fn setup_level_ui(mut commands: Commands) {
@@ -160,7 +191,7 @@ pub struct LevelUI;
 #[derive(Default, Resource)]
 pub struct CurrentLevel(pub GameLevel);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct GameLevel {
     //pub message: &'static str,
     pub shapes: usize,
@@ -176,7 +207,12 @@ impl Default for GameLevel {
 }
 
 impl GameLevel {
-    pub fn get_text(&self, input_detector: Res<InputDetector>) -> Option<String> {
+    pub fn get_text(
+        &self,
+        input_detector: Res<InputDetector>,
+        campaign: &Campaign,
+        bindings: &KeyBindings,
+    ) -> Option<String> {
         match self.level_type {
             LevelType::Tutorial => match self.shapes {
                 1 => Some("place the shape".to_string()),
@@ -186,35 +222,68 @@ impl GameLevel {
                     if input_detector.is_touch {
                         Some("Rotate with your finger".to_string())
                     } else {
-                        Some("Rotate with the mousewheel, or Q/E".to_string())
+                        Some(format!(
+                            "Rotate with the mousewheel, or {}/{}",
+                            bindings.description(InputAction::RotateAnticlockwise),
+                            bindings.description(InputAction::RotateClockwise)
+                        ))
                     }
                 }
                 _ => None,
             },
-            LevelType::Infinite => None,
+            LevelType::Infinite { .. } => None,
             LevelType::Challenge => Some("Daily Challenge".to_string()),
             LevelType::ChallengeComplete(streak) => {
                 Some(format!("Congratulations.\nYour streak is {streak}!"))
             }
+            LevelType::Campaign(index) => campaign
+                .get_level(index)
+                .and_then(|x| x.text.clone())
+                .or_else(|| Some(format!("Level {}", index + 1))),
+            LevelType::Speedrun(index) => Some(format!(
+                "Speedrun {}/{}",
+                index + 1,
+                SPEEDRUN_SHAPES.len()
+            )),
         }
     }
 
     pub fn get_buttons(&self) -> Option<Vec<MenuButton>> {
         match self.level_type {
             LevelType::ChallengeComplete(_streak) => {
-                Some(vec![MenuButton::DownloadImage, MenuButton::Infinite])
+                Some(vec![
+                    MenuButton::Share,
+                    MenuButton::DownloadImage,
+                    MenuButton::Calendar,
+                    MenuButton::Infinite,
+                ])
             }
             _ => Default::default(),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LevelType {
     Tutorial,
-    Infinite,
+    Infinite { seed: u64 },
     Challenge,
     ChallengeComplete(usize),
+    Campaign(usize),
+    Speedrun(usize),
+}
+
+impl LevelType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LevelType::Tutorial => "Tutorial",
+            LevelType::Infinite { .. } => "Infinite",
+            LevelType::Challenge => "Challenge",
+            LevelType::ChallengeComplete(_) => "Challenge Complete",
+            LevelType::Campaign(_) => "Campaign",
+            LevelType::Speedrun(_) => "Speedrun",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -224,12 +293,25 @@ pub enum ChangeLevelEvent {
     ResetLevel,
     StartTutorial,
     StartInfinite,
+    RestartInfinite,
     StartChallenge,
+    StartCampaign,
+    StartSpeedrun,
+    /// Start a level with a particular layout so a replay can be watched
+    StartReplay {
+        level: GameLevel,
+        layout: Vec<SavedShape>,
+    },
 }
 
 impl ChangeLevelEvent {
     #[must_use]
-    pub fn apply(&self, level: &GameLevel, pkv: &mut ResMut<PkvStore>) -> GameLevel {
+    pub fn apply(
+        &self,
+        level: &GameLevel,
+        pkv: &mut ResMut<PkvStore>,
+        campaign: &Campaign,
+    ) -> GameLevel {
         //info!("Change level {:?}", self);
         match self {
             ChangeLevelEvent::Next => match level.level_type {
@@ -240,9 +322,12 @@ impl ChangeLevelEvent {
                             x
                         });
                         if saved_data.has_beat_todays_challenge() {
-                            GameLevel {
-                                shapes: level.shapes + 1,
-                                level_type: LevelType::Infinite,
+                            // Don't replace infinite progress when the tutorial is replayed
+                            match saved_data.infinite {
+                                Some(_) => {
+                                    ChangeLevelEvent::StartInfinite.apply(level, pkv, campaign)
+                                }
+                                None => new_infinite_level(level.shapes + 1, pkv),
                             }
                         } else {
                             GameLevel {
@@ -257,40 +342,115 @@ impl ChangeLevelEvent {
                         }
                     }
                 }
-                LevelType::Infinite => GameLevel {
-                    shapes: level.shapes + 1,
-                    level_type: LevelType::Infinite,
-                },
+                LevelType::Infinite { .. } => new_infinite_level(level.shapes + 1, pkv),
                 LevelType::Challenge => {
-                    let saved_data = SavedData::update(pkv, |x| x.with_todays_challenge_beat());
+                    let streak = ChallengeHistory::load(pkv).streak_after_beating(get_today_date());
+                    SavedData::update(pkv, |x| x.with_todays_challenge_beat(streak));
 
                     GameLevel {
                         shapes: level.shapes + 1,
-                        level_type: LevelType::ChallengeComplete(saved_data.challenge_streak),
+                        level_type: LevelType::ChallengeComplete(streak),
                     }
                 }
                 LevelType::ChallengeComplete(x) => GameLevel {
                     shapes: level.shapes + 1,
                     level_type: LevelType::ChallengeComplete(x),
                 },
+                LevelType::Campaign(index) => {
+                    let next = index + 1;
+                    SavedData::update(pkv, |mut x| {
+                        x.campaign_level = x.campaign_level.max(next);
+                        x
+                    });
+
+                    if next < campaign.levels.len() {
+                        campaign_level(campaign, next)
+                    } else {
+                        ChangeLevelEvent::StartInfinite.apply(level, pkv, campaign)
+                    }
+                }
+                LevelType::Speedrun(index) => {
+                    if index + 1 < SPEEDRUN_SHAPES.len() {
+                        speedrun_level(index + 1)
+                    } else {
+                        ChangeLevelEvent::StartInfinite.apply(level, pkv, campaign)
+                    }
+                }
             },
             // ChangeLevelEvent::Previous => GameLevel {
             //     shapes: level.shapes.saturating_sub(1).max(1),
             //     level_type: level.level_type,
             // },
-            ChangeLevelEvent::ResetLevel => *level,
+            ChangeLevelEvent::ResetLevel => {
+                if let LevelType::Infinite { .. } = level.level_type {
+                    SavedData::update(pkv, |mut x| {
+                        if let Some(progress) = x.infinite.as_mut() {
+                            progress.layout = None;
+                        }
+                        x
+                    });
+                }
+                *level
+            }
             ChangeLevelEvent::StartTutorial => GameLevel {
                 shapes: 1,
                 level_type: LevelType::Tutorial,
             },
-            ChangeLevelEvent::StartInfinite => GameLevel {
-                shapes: 5,
-                level_type: LevelType::Infinite,
+            ChangeLevelEvent::StartInfinite => match SavedData::get_or_create(pkv).infinite {
+                Some(progress) => GameLevel {
+                    shapes: progress.shapes,
+                    level_type: LevelType::Infinite {
+                        seed: progress.seed,
+                    },
+                },
+                None => new_infinite_level(INFINITE_START_SHAPES, pkv),
             },
+            ChangeLevelEvent::RestartInfinite => new_infinite_level(INFINITE_START_SHAPES, pkv),
             ChangeLevelEvent::StartChallenge => GameLevel {
                 shapes: CHALLENGE_SHAPES,
                 level_type: LevelType::Challenge,
             },
+            ChangeLevelEvent::StartCampaign => {
+                let saved_data = SavedData::get_or_create(pkv);
+                let index = saved_data
+                    .campaign_level
+                    .min(campaign.levels.len().saturating_sub(1));
+                campaign_level(campaign, index)
+            }
+            ChangeLevelEvent::StartSpeedrun => speedrun_level(0),
+            ChangeLevelEvent::StartReplay { level, .. } => *level,
         }
     }
 }
+
+/// Create a new infinite level with a random seed and save it as the player's progress
+fn new_infinite_level(shapes: usize, pkv: &mut ResMut<PkvStore>) -> GameLevel {
+    let seed = rand::random();
+    SavedData::update(pkv, |mut x| {
+        x.infinite = Some(InfiniteProgress {
+            shapes,
+            seed,
+            layout: None,
+        });
+        x
+    });
+
+    GameLevel {
+        shapes,
+        level_type: LevelType::Infinite { seed },
+    }
+}
+
+fn campaign_level(campaign: &Campaign, index: usize) -> GameLevel {
+    GameLevel {
+        shapes: campaign.get_level(index).map(|x| x.shapes.len()).unwrap_or_default(),
+        level_type: LevelType::Campaign(index),
+    }
+}
+
+fn speedrun_level(index: usize) -> GameLevel {
+    GameLevel {
+        shapes: SPEEDRUN_SHAPES[index],
+        level_type: LevelType::Speedrun(index),
+    }
+}
@@ -11,6 +11,7 @@ use bevy_rapier2d::prelude::*;
 pub const WINDOW_WIDTH: f32 = 360f32;
 pub const WINDOW_HEIGHT: f32 = 640f32;
 pub const WALL_WIDTH: f32 = 360f32;
+pub const PHYSICS_SCALE: f32 = WINDOW_HEIGHT / 10.0;
 mod camera;
 mod draggable;
 mod grid;
@@ -25,6 +26,9 @@ use draggable::*;
 use saved_data::*;
 mod level;
 use level::*;
+mod campaign;
+use campaign::*;
+mod challenge;
 mod walls;
 use screenshots::ScreenshotPlugin;
 use walls::*;
@@ -43,7 +47,53 @@ use input::*;
 mod collision;
 use collision::*;
 
+mod undo;
+use undo::*;
+
+mod stats;
+use stats::*;
+
+mod infinite;
+use infinite::*;
+
+mod export;
+use export::*;
+
+mod gallery;
+use gallery::*;
+
+mod challenge_history;
+use challenge_history::*;
+
+mod speedrun;
+use speedrun::*;
+
+mod replay;
+use replay::*;
+
+mod share;
+use share::*;
+
+mod achievements;
+use achievements::*;
+
+mod key_bindings;
+use key_bindings::*;
+
+mod settings;
+use settings::*;
+
+mod joints;
+use joints::*;
+
+mod platforms;
+use platforms::*;
+
+mod rescue;
+use rescue::*;
+
 pub mod game_shape;
+pub mod simulation;
 
 pub const ZOOM_ENTITY_LAYER: u8 = 1;
 
@@ -62,10 +112,10 @@ fn main() {
             width: WINDOW_WIDTH,
             height: WINDOW_HEIGHT,
             resize_constraints: WindowResizeConstraints {
-                min_width: WINDOW_WIDTH,
+                min_width: MIN_PLAY_AREA_SIZE,
                 max_width: f32::MAX,
 
-                min_height: WINDOW_HEIGHT,
+                min_height: MIN_PLAY_AREA_SIZE,
                 max_height: f32::MAX,
             },
             ..Default::default()
@@ -88,7 +138,7 @@ fn main() {
         .add_plugin(InputPlugin)
         .add_plugin(CameraPlugin)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
-            WINDOW_HEIGHT / 10.0,
+            PHYSICS_SCALE,
         ))
         .add_startup_system(setup)
         .add_plugin(DragPlugin)
@@ -97,6 +147,21 @@ fn main() {
         .add_plugin(TweeningPlugin)
         .add_plugin(ScreenshotPlugin)
         .add_plugin(CollisionPlugin)
+        .add_plugin(UndoPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(InfinitePlugin)
+        .add_plugin(ExportPlugin)
+        .add_plugin(GalleryPlugin)
+        .add_plugin(ChallengeHistoryPlugin)
+        .add_plugin(SpeedrunPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(SharePlugin)
+        .add_plugin(AchievementsPlugin)
+        .add_plugin(KeyBindingsPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(JointsPlugin)
+        .add_plugin(PlatformsPlugin)
+        .add_plugin(RescuePlugin)
         .insert_resource(PkvStore::new("Wainwrong", "Equilibrium"))
 
 
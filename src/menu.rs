@@ -6,7 +6,8 @@ pub struct ButtonPlugin;
 impl Plugin for ButtonPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(setup.after(setup_level_ui))
-            .add_system_to_stage(CoreStage::First, button_system);
+            .add_system_to_stage(CoreStage::First, button_system)
+            .add_system(close_panels);
     }
 }
 
@@ -24,6 +25,7 @@ const HOVERED_BUTTON: Color = Color::rgb(0.8, 0.8, 0.8);
 const PRESSED_BUTTON: Color = Color::rgb(0.7, 0.7, 0.7);
 
 const BUTTON_BACKGROUND: Color = Color::rgb(0.1, 0.1, 0.1);
+const PANEL_BACKGROUND: Color = Color::rgba(0.9, 0.9, 0.9, 0.9);
 
 const BUTTON_WIDTH: f32 = 65.;
 const BUTTON_HEIGHT: f32 = 65.;
@@ -36,6 +38,18 @@ fn button_system(
     mut change_level_events: EventWriter<crate::ChangeLevelEvent>,
     mut menu_query: Query<&mut Visibility, With<MainMenu>>,
     mut download_image_events: EventWriter<crate::screenshots::DownloadPngEvent>,
+    mut undo_events: EventWriter<crate::UndoEvent>,
+    mut show_stats_events: EventWriter<crate::ShowStatsEvent>,
+    mut export_events: EventWriter<crate::ExportSaveEvent>,
+    mut import_events: EventWriter<crate::ImportSaveEvent>,
+    mut show_gallery_events: EventWriter<crate::ShowGalleryEvent>,
+    mut show_calendar_events: EventWriter<crate::ShowCalendarEvent>,
+    mut watch_replay_events: EventWriter<crate::WatchReplayEvent>,
+    mut save_replay_events: EventWriter<crate::SaveReplayEvent>,
+    mut share_events: EventWriter<crate::ShareEvent>,
+    mut show_achievements_events: EventWriter<crate::ShowAchievementsEvent>,
+    mut show_controls_events: EventWriter<crate::ShowControlsEvent>,
+    mut show_settings_events: EventWriter<crate::ShowSettingsEvent>,
 ) {
     for (interaction, mut color, button) in interaction_query.iter_mut() {
         //info!("{:?}", interaction);
@@ -59,12 +73,35 @@ fn button_system(
                     MenuButton::Infinite => {
                         change_level_events.send(crate::ChangeLevelEvent::StartInfinite)
                     }
+                    MenuButton::RestartInfinite => {
+                        change_level_events.send(crate::ChangeLevelEvent::RestartInfinite)
+                    }
                     MenuButton::DailyChallenge => {
                         change_level_events.send(crate::ChangeLevelEvent::StartChallenge)
                     }
+                    MenuButton::Campaign => {
+                        change_level_events.send(crate::ChangeLevelEvent::StartCampaign)
+                    }
+                    MenuButton::Speedrun => {
+                        change_level_events.send(crate::ChangeLevelEvent::StartSpeedrun)
+                    }
                     MenuButton::ResetLevel => {
                         change_level_events.send(crate::ChangeLevelEvent::ResetLevel)
                     }
+                    MenuButton::Undo => undo_events.send(crate::UndoEvent),
+                    MenuButton::Stats => show_stats_events.send(crate::ShowStatsEvent),
+                    MenuButton::Gallery => show_gallery_events.send(crate::ShowGalleryEvent),
+                    MenuButton::Calendar => show_calendar_events.send(crate::ShowCalendarEvent),
+                    MenuButton::Achievements => {
+                        show_achievements_events.send(crate::ShowAchievementsEvent)
+                    }
+                    MenuButton::Controls => show_controls_events.send(crate::ShowControlsEvent),
+                    MenuButton::Settings => show_settings_events.send(crate::ShowSettingsEvent),
+                    MenuButton::WatchReplay => watch_replay_events.send(crate::WatchReplayEvent),
+                    MenuButton::SaveReplay => save_replay_events.send(crate::SaveReplayEvent),
+                    MenuButton::ExportSave => export_events.send(crate::ExportSaveEvent),
+                    MenuButton::ImportSave => import_events.send(crate::ImportSaveEvent),
+                    MenuButton::Share => share_events.send(crate::ShareEvent),
                     MenuButton::DownloadImage => {
                         download_image_events.send(crate::screenshots::DownloadPngEvent)
                     }
@@ -108,12 +145,25 @@ fn spawn_menu(commands: &mut Commands, asset_server: &AssetServer) {
             for button in [
                 // ToggleMenu,
                 ResetLevel,
+                Undo,
                 #[cfg(target_arch = "wasm32")]
                 GoFullscreen,
                 Tutorial,
                 Infinite,
+                RestartInfinite,
                 DailyChallenge,
+                Campaign,
+                Speedrun,
                 DownloadImage,
+                WatchReplay,
+                SaveReplay,
+                Gallery,
+                Stats,
+                Achievements,
+                Controls,
+                Settings,
+                ExportSave,
+                ImportSave,
             ] {
                 spawn_button(parent, button, asset_server);
             }
@@ -142,6 +192,50 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     spawn_menu(&mut commands, asset_server.as_ref())
 }
 
+/// A screen shown over the game. Clicking on it closes it.
+#[derive(Component)]
+pub struct Panel;
+
+/// Spawn a new panel, closing any existing panels
+pub fn spawn_panel(
+    commands: &mut Commands,
+    existing_panels: &Query<Entity, With<Panel>>,
+    spawn_children: impl FnOnce(&mut ChildBuilder),
+) {
+    for entity in existing_panels.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: PANEL_BACKGROUND.into(),
+            z_index: ZIndex::Global(8),
+            ..Default::default()
+        })
+        .insert(Panel)
+        .insert(Interaction::default())
+        .with_children(spawn_children);
+}
+
+fn close_panels(
+    mut commands: Commands,
+    panels: Query<(Entity, &Interaction), (Changed<Interaction>, With<Panel>)>,
+) {
+    for (entity, interaction) in panels.iter() {
+        if *interaction == Interaction::Clicked {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
 pub fn spawn_button(
     parent: &mut ChildBuilder,
     menu_button: MenuButton,
@@ -167,8 +261,8 @@ pub fn spawn_button(
                 text: Text::from_section(
                     menu_button.text(),
                     TextStyle {
-                        font: asset_server.load("fonts/fontello-font.ttf"),
-                        font_size: 30.0,
+                        font: asset_server.load(menu_button.font()),
+                        font_size: menu_button.font_size(),
                         color: BUTTON_BACKGROUND,
                     },
                 ),
@@ -182,11 +276,26 @@ pub fn spawn_button(
 pub enum MenuButton {
     ToggleMenu,
     ResetLevel,
+    Undo,
     GoFullscreen,
     Tutorial,
     Infinite,
+    RestartInfinite,
     DailyChallenge,
     DownloadImage,
+    Share,
+    Campaign,
+    Speedrun,
+    Stats,
+    Gallery,
+    Calendar,
+    Achievements,
+    Controls,
+    Settings,
+    WatchReplay,
+    SaveReplay,
+    ExportSave,
+    ImportSave,
 }
 
 impl MenuButton {
@@ -199,6 +308,58 @@ impl MenuButton {
             MenuButton::Infinite => "\u{e802}",       //"Infinite",
             MenuButton::DailyChallenge => "\u{e803}", // "Challenge",
             MenuButton::DownloadImage => "\u{e804}",  // "Image",
+            MenuButton::Share => "\u{f1e0}",          // "Share",
+            MenuButton::Undo => "Undo",
+            MenuButton::Campaign => "Campaign",
+            MenuButton::Speedrun => "Speedrun",
+            MenuButton::Stats => "Stats",
+            MenuButton::Gallery => "Gallery",
+            MenuButton::Calendar => "Calendar",
+            MenuButton::Achievements => "Achieve-\nments",
+            MenuButton::Controls => "Controls",
+            MenuButton::Settings => "Settings",
+            MenuButton::WatchReplay => "Replay",
+            MenuButton::SaveReplay => "Save\nReplay",
+            MenuButton::RestartInfinite => "Restart",
+            MenuButton::ExportSave => "Export",
+            MenuButton::ImportSave => "Import",
+        }
+    }
+
+    /// Whether this button is drawn with an icon from the icon font rather than a text label
+    fn is_icon(&self) -> bool {
+        !matches!(
+            self,
+            MenuButton::Undo
+                | MenuButton::RestartInfinite
+                | MenuButton::Campaign
+                | MenuButton::Speedrun
+                | MenuButton::Stats
+                | MenuButton::Gallery
+                | MenuButton::Calendar
+                | MenuButton::Achievements
+                | MenuButton::Controls
+                | MenuButton::Settings
+                | MenuButton::WatchReplay
+                | MenuButton::SaveReplay
+                | MenuButton::ExportSave
+                | MenuButton::ImportSave
+        )
+    }
+
+    pub fn font(&self) -> &'static str {
+        if self.is_icon() {
+            "fonts/fontello-font.ttf"
+        } else {
+            "fonts/FiraMono-Medium.ttf"
+        }
+    }
+
+    pub fn font_size(&self) -> f32 {
+        if self.is_icon() {
+            30.0
+        } else {
+            12.0
         }
     }
 }
@@ -0,0 +1,208 @@
+use bevy_rapier2d::rapier::dynamics::{
+    ImpulseJointHandle, ImpulseJointSet, JointAxis, RigidBodyHandle, RigidBodySet,
+};
+use bevy_rapier2d::rapier::prelude::Isometry;
+use serde::*;
+
+use crate::*;
+
+pub struct PlatformsPlugin;
+
+impl Plugin for PlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_level_platforms)
+            .add_system(move_platforms.after(spawn_level_platforms));
+    }
+}
+
+pub const SEESAW_STIFFNESS: f32 = 50.0;
+pub const SEESAW_DAMPING: f32 = 10.0;
+const MIN_PERIOD: f32 = 0.1;
+
+/// A platform which moves on a schedule
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Platform {
+    /// Where the centre of the platform starts
+    pub position: (f32, f32),
+    pub width: f32,
+    pub height: f32,
+    pub motion: Motion,
+    /// The seconds taken to go there and back, or to turn once
+    pub period: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Motion {
+    /// Slide to `offset` from the start and back again
+    Slide { offset: (f32, f32) },
+    /// Turn about the centre at a constant speed
+    Spin,
+    /// Tilt back and forth by up to `angle` radians.
+    /// Driven by a motor on a pivot rather than moved kinematically, so shapes can push it.
+    Seesaw { angle: f32 },
+}
+
+impl Platform {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn is_kinematic(&self) -> bool {
+        !matches!(self.motion, Motion::Seesaw { .. })
+    }
+
+    /// The position and angle of the platform after `seconds`.
+    /// For a seesaw this is the angle its motor is turning it to.
+    pub fn pose_at(&self, seconds: f32) -> (Vec2, f32) {
+        let start = Vec2::new(self.position.0, self.position.1);
+        let phase = seconds / self.period.max(MIN_PERIOD) * std::f32::consts::TAU;
+
+        match self.motion {
+            Motion::Slide { offset } => {
+                let offset = Vec2::new(offset.0, offset.1);
+                (start + offset * (1.0 - phase.cos()) * 0.5, 0.0)
+            }
+            Motion::Spin => (start, phase),
+            Motion::Seesaw { angle } => (start, angle * phase.sin()),
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct MovingPlatform {
+    pub platform: Platform,
+    /// When the platform started moving, in elapsed seconds
+    pub started: f64,
+}
+
+/// The fixed body a seesaw turns on
+#[derive(Component)]
+pub struct PlatformPivot;
+
+/// A platform in a `PhysicsWorld`, which is moved as the world is stepped
+#[derive(Debug, Clone)]
+pub struct SimulatedPlatform {
+    pub platform: Platform,
+    pub body: RigidBodyHandle,
+    /// The motor joint of a seesaw
+    pub joint: Option<ImpulseJointHandle>,
+    /// The seconds the platform had been moving for when the world was created
+    pub elapsed: f32,
+}
+
+impl SimulatedPlatform {
+    /// Move the platform to where it should be `seconds` after the world was created
+    pub fn apply(&self, bodies: &mut RigidBodySet, joints: &mut ImpulseJointSet, seconds: f32) {
+        let (position, angle) = self.platform.pose_at(self.elapsed + seconds);
+
+        match self.joint {
+            Some(handle) => {
+                if let Some(joint) = joints.get_mut(handle) {
+                    joint.data.set_motor_position(
+                        JointAxis::AngX,
+                        angle,
+                        SEESAW_STIFFNESS,
+                        SEESAW_DAMPING,
+                    );
+                }
+            }
+            None => {
+                if let Some(body) = bodies.get_mut(self.body) {
+                    body.set_next_kinematic_position(Isometry::new(
+                        (position / PHYSICS_SCALE).into(),
+                        angle,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Replace the platforms when the level changes
+fn spawn_level_platforms(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    campaign: Res<Campaign>,
+    existing: Query<Entity, Or<(With<MovingPlatform>, With<PlatformPivot>)>>,
+    time: Res<Time>,
+) {
+    if !current_level.is_changed() {
+        return;
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let LevelType::Campaign(index) = current_level.0.level_type else {return;};
+    let Some(campaign_level) = campaign.get_level(index) else {return;};
+
+    for platform in campaign_level.platforms.iter() {
+        walls::spawn_platform(&mut commands, *platform, time.elapsed_seconds_f64());
+    }
+}
+
+fn move_platforms(
+    time: Res<Time>,
+    mut platforms: Query<(&MovingPlatform, &mut Transform, Option<&mut ImpulseJoint>)>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (moving, mut transform, joint) in platforms.iter_mut() {
+        let (position, angle) = moving.platform.pose_at((now - moving.started) as f32);
+
+        match joint {
+            Some(mut joint) => {
+                joint.data.set_motor_position(
+                    JointAxis::AngX,
+                    angle,
+                    SEESAW_STIFFNESS,
+                    SEESAW_DAMPING,
+                );
+            }
+            None => {
+                transform.translation = position.extend(transform.translation.z);
+                transform.rotation = Quat::from_rotation_z(angle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(motion: Motion) -> Platform {
+        Platform {
+            position: (10.0, 20.0),
+            width: 100.0,
+            height: 10.0,
+            motion,
+            period: 4.0,
+        }
+    }
+
+    #[test]
+    fn test_slide_goes_there_and_back() {
+        let platform = platform(Motion::Slide {
+            offset: (50.0, 0.0),
+        });
+
+        assert_eq!(platform.pose_at(0.0), (Vec2::new(10.0, 20.0), 0.0));
+        let (halfway, _) = platform.pose_at(2.0);
+        assert!(
+            halfway.abs_diff_eq(Vec2::new(60.0, 20.0), 0.001),
+            "{halfway}"
+        );
+        let (back, _) = platform.pose_at(4.0);
+        assert!(back.abs_diff_eq(Vec2::new(10.0, 20.0), 0.001), "{back}");
+    }
+
+    #[test]
+    fn test_seesaw_tilts_both_ways() {
+        let platform = platform(Motion::Seesaw { angle: 0.5 });
+
+        assert!(!platform.is_kinematic());
+        assert!((platform.pose_at(1.0).1 - 0.5).abs() < 0.001);
+        assert!((platform.pose_at(3.0).1 + 0.5).abs() < 0.001);
+    }
+}
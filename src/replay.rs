@@ -0,0 +1,458 @@
+use bevy::ecs::schedule::ShouldRun;
+use chrono::NaiveDate;
+use serde::*;
+
+use crate::challenge::CHALLENGE_GENERATOR_VERSION;
+use crate::shape_maker::ShapeIndex;
+use crate::*;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .init_resource::<ReplayPlayback>()
+            .add_event::<WatchReplayEvent>()
+            .add_event::<SaveReplayEvent>()
+            .add_system(
+                record_replay
+                    .after(input::mousebutton_listener)
+                    .after(input::touch_listener)
+                    .after(input::keyboard_listener)
+                    .after(input::mousewheel_listener)
+                    .after(input::gamepad_listener)
+                    .after(play_replay),
+            )
+            .add_system(
+                play_replay
+                    .before(undo::handle_undo)
+                    .before(drag_start)
+                    .before(drag_move)
+                    .before(drag_end)
+                    .before(handle_rotate_events),
+            )
+            .add_system(watch_replay.after(play_replay))
+            .add_system(save_replay);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system(watch_replay_argument.after(play_replay));
+    }
+}
+
+/// Increase this if the replay format changes
+pub const REPLAY_VERSION: u32 = 1;
+const REPLAY_KEY: &str = "replay";
+const REPLAY_FILE_NAME: &str = "equilibrium-replay.json";
+/// How long to wait after the last input before a win countdown starts
+const REPLAY_END_SECONDS: f64 = 1.0;
+
+pub struct WatchReplayEvent;
+pub struct SaveReplayEvent;
+
+/// Everything needed to watch a level again
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub level: GameLevel,
+    pub date: NaiveDate,
+    /// The version of the challenge generator, for replays of daily challenges
+    #[serde(default)]
+    pub challenge_generator: Option<u32>,
+    /// The shapes at the start of the level
+    pub layout: Vec<SavedShape>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// The inputs from a single frame
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Seconds since the start of the level
+    pub time: f32,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    DragStart(DragSource, (f32, f32)),
+    /// The shape whose centre is at the position was picked up directly
+    PickUp(DragSource, (f32, f32)),
+    DragMove(DragSource, (f32, f32)),
+    DragEnd(DragSource),
+    Rotate(f32, Option<f32>),
+    Undo,
+}
+
+impl Replay {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("Could not serialize replay")
+    }
+
+    pub fn decode(text: &str) -> Result<Self, anyhow::Error> {
+        let replay: Replay = serde_json::from_str(text)?;
+        if replay.version != REPLAY_VERSION {
+            anyhow::bail!("Unknown replay version {}", replay.version);
+        }
+        if let Some(generator) = replay.challenge_generator {
+            if generator > CHALLENGE_GENERATOR_VERSION {
+                anyhow::bail!("Unknown challenge generator version {generator}");
+            }
+        }
+        Ok(replay)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    replay: Option<Replay>,
+    started: f64,
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayPlayback(PlaybackState);
+
+#[derive(Default)]
+enum PlaybackState {
+    #[default]
+    Stopped,
+    /// Waiting for the replay's level to start
+    Starting(Replay),
+    Playing {
+        replay: Replay,
+        started: f64,
+        next_frame: usize,
+    },
+    /// Every input has been played. Waiting for the tower to win or fall.
+    Finishing { since: f64 },
+}
+
+impl ReplayPlayback {
+    pub fn is_active(&self) -> bool {
+        !matches!(self.0, PlaybackState::Stopped)
+    }
+}
+
+/// Run criteria which stops player input while a replay is playing
+pub fn not_watching_replay(playback: Res<ReplayPlayback>) -> ShouldRun {
+    if playback.is_active() {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+fn record_replay(
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Res<ReplayPlayback>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    shapes: Query<(&ShapeIndex, &Transform, &Draggable)>,
+    mut drag_start_events: EventReader<DragStartEvent>,
+    mut drag_move_events: EventReader<DragMoveEvent>,
+    mut drag_end_events: EventReader<DragEndEvent>,
+    mut rotate_events: EventReader<RotateEvent>,
+    mut undo_events: EventReader<UndoEvent>,
+    mut change_level_events: EventReader<ChangeLevelEvent>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    let mut events: Vec<ReplayEvent> = undo_events.iter().map(|_| ReplayEvent::Undo).collect();
+    events.extend(drag_start_events.iter().map(|x| {
+        let position = (x.position.x, x.position.y);
+        if x.target.is_some() {
+            ReplayEvent::PickUp(x.drag_source, position)
+        } else {
+            ReplayEvent::DragStart(x.drag_source, position)
+        }
+    }));
+    events.extend(drag_move_events.iter().map(|x| {
+        ReplayEvent::DragMove(x.drag_source, (x.new_position.x, x.new_position.y))
+    }));
+    events.extend(
+        drag_end_events
+            .iter()
+            .map(|x| ReplayEvent::DragEnd(x.drag_source)),
+    );
+    // Unsnapped rotations come from touch drags, which are recreated from the drag events
+    events.extend(
+        rotate_events
+            .iter()
+            .filter(|x| x.snap_resolution.is_some())
+            .map(|x| ReplayEvent::Rotate(x.angle, x.snap_resolution)),
+    );
+    let level_won = change_level_events
+        .iter()
+        .any(|x| matches!(x, ChangeLevelEvent::Next));
+
+    if playback.is_active() {
+        recorder.replay = None;
+        return;
+    }
+
+    if level_won {
+        if let Some(replay) = recorder.replay.take() {
+            if let Err(err) = pkv.set(REPLAY_KEY, &replay) {
+                error!("Could not save replay: {}", err);
+            }
+        }
+    }
+
+    if current_level.is_changed() {
+        recorder.started = now;
+        recorder.replay = Some(Replay {
+            version: REPLAY_VERSION,
+            level: current_level.0,
+            date: get_today_date(),
+            challenge_generator: (current_level.0.level_type == LevelType::Challenge)
+                .then_some(CHALLENGE_GENERATOR_VERSION),
+            layout: shapes
+                .iter()
+                .map(|(index, transform, draggable)| SavedShape::new(index.0, transform, draggable))
+                .collect(),
+            frames: vec![],
+        });
+    }
+
+    if events.is_empty() {
+        return;
+    }
+    let time = (now - recorder.started) as f32;
+    if let Some(replay) = recorder.replay.as_mut() {
+        replay.frames.push(ReplayFrame { time, events });
+    }
+}
+
+fn play_replay(
+    mut playback: ResMut<ReplayPlayback>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    mut drag_start_events: EventWriter<DragStartEvent>,
+    mut drag_move_events: EventWriter<DragMoveEvent>,
+    mut drag_end_events: EventWriter<DragEndEvent>,
+    mut rotate_events: EventWriter<RotateEvent>,
+    mut undo_events: EventWriter<UndoEvent>,
+    win_timers: Query<(), With<WinTimer>>,
+    shapes: Query<(Entity, &Transform), With<Draggable>>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    if current_level.is_changed() {
+        playback.0 = match std::mem::take(&mut playback.0) {
+            PlaybackState::Starting(replay) => PlaybackState::Playing {
+                replay,
+                started: now,
+                next_frame: 0,
+            },
+            // The player changed level, so stop watching
+            _ => PlaybackState::Stopped,
+        };
+    }
+
+    if let PlaybackState::Finishing { since } = playback.0 {
+        // Input stays blocked until the countdown ends, so watching doesn't count as winning
+        if win_timers.is_empty() && now - since >= REPLAY_END_SECONDS {
+            playback.0 = PlaybackState::Stopped;
+        }
+        return;
+    }
+
+    let PlaybackState::Playing { replay, started, next_frame } = &mut playback.0 else {return;};
+    let elapsed = (now - *started) as f32;
+
+    while let Some(frame) = replay.frames.get(*next_frame) {
+        if frame.time > elapsed {
+            break;
+        }
+        for event in frame.events.iter() {
+            match event {
+                ReplayEvent::DragStart(drag_source, (x, y)) => {
+                    drag_start_events.send(DragStartEvent {
+                        drag_source: *drag_source,
+                        position: Vec2::new(*x, *y),
+                        target: None,
+                    })
+                }
+                ReplayEvent::PickUp(drag_source, (x, y)) => {
+                    let position = Vec2::new(*x, *y);
+                    let target = shapes
+                        .iter()
+                        .map(|(entity, transform)| {
+                            (entity, transform.translation.truncate().distance(position))
+                        })
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(entity, _)| entity);
+                    drag_start_events.send(DragStartEvent {
+                        drag_source: *drag_source,
+                        position,
+                        target,
+                    })
+                }
+                ReplayEvent::DragMove(drag_source, (x, y)) => {
+                    drag_move_events.send(DragMoveEvent {
+                        drag_source: *drag_source,
+                        new_position: Vec2::new(*x, *y),
+                    })
+                }
+                ReplayEvent::DragEnd(drag_source) => drag_end_events.send(DragEndEvent {
+                    drag_source: *drag_source,
+                }),
+                ReplayEvent::Rotate(angle, snap_resolution) => rotate_events.send(RotateEvent {
+                    angle: *angle,
+                    snap_resolution: *snap_resolution,
+                }),
+                ReplayEvent::Undo => undo_events.send(UndoEvent),
+            }
+        }
+        *next_frame += 1;
+    }
+
+    if *next_frame >= replay.frames.len() {
+        playback.0 = PlaybackState::Finishing { since: now };
+    }
+}
+
+fn watch_replay(
+    mut events: EventReader<WatchReplayEvent>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut change_level_events: EventWriter<ChangeLevelEvent>,
+    pkv: Res<PkvStore>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    match pkv.get::<Replay>(REPLAY_KEY) {
+        Ok(replay) => start_playback(replay, &mut playback, &mut change_level_events),
+        Err(_) => warn!("No replay to watch"),
+    }
+}
+
+fn start_playback(
+    replay: Replay,
+    playback: &mut ReplayPlayback,
+    change_level_events: &mut EventWriter<ChangeLevelEvent>,
+) {
+    change_level_events.send(ChangeLevelEvent::StartReplay {
+        level: replay.level,
+        layout: replay.layout.clone(),
+    });
+    playback.0 = PlaybackState::Starting(replay);
+}
+
+/// Watch the replay file given with `--replay <file>`.
+/// This waits for the first frame so it starts after the level chosen at startup.
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_replay_argument(
+    mut done: Local<bool>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut change_level_events: EventWriter<ChangeLevelEvent>,
+) {
+    if *done {
+        return;
+    }
+    *done = true;
+
+    let mut args = std::env::args().skip_while(|x| x != "--replay").skip(1);
+    let Some(path) = args.next() else {return;};
+
+    match std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| Replay::decode(&text))
+    {
+        Ok(replay) => start_playback(replay, &mut playback, &mut change_level_events),
+        Err(err) => error!("Could not load replay {path}: {}", err),
+    }
+}
+
+/// Save the level being played, or the last completed level, to a file
+fn save_replay(
+    mut events: EventReader<SaveReplayEvent>,
+    recorder: Res<ReplayRecorder>,
+    pkv: Res<PkvStore>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let replay = recorder
+        .replay
+        .clone()
+        .filter(|x| !x.frames.is_empty())
+        .or_else(|| pkv.get::<Replay>(REPLAY_KEY).ok());
+    let Some(replay) = replay else {
+        warn!("No replay to save");
+        return;
+    };
+    let text = replay.encode();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::wasm::download::download_bytes(
+            REPLAY_FILE_NAME.into(),
+            text.into_bytes(),
+            "application/json",
+        );
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = desktop_file_path(REPLAY_FILE_NAME);
+        match std::fs::write(&path, text) {
+            Ok(()) => info!("Saved replay to {}", path.display()),
+            Err(err) => error!("Could not save replay to {}: {}", path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let replay = Replay {
+            version: REPLAY_VERSION,
+            level: GameLevel {
+                shapes: 10,
+                level_type: LevelType::Challenge,
+            },
+            date: NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+            challenge_generator: Some(CHALLENGE_GENERATOR_VERSION),
+            layout: vec![SavedShape {
+                shape_index: 3,
+                position: (10.0, -20.0),
+                angle: 1.5,
+                locked: false,
+            }],
+            frames: vec![
+                ReplayFrame {
+                    time: 0.5,
+                    events: vec![ReplayEvent::DragStart(DragSource::Mouse, (10.0, -20.0))],
+                },
+                ReplayFrame {
+                    time: 0.6,
+                    events: vec![
+                        ReplayEvent::DragMove(DragSource::Touch { touch_id: 1 }, (12.0, -20.0)),
+                        ReplayEvent::Rotate(0.4, Some(0.4)),
+                        ReplayEvent::DragEnd(DragSource::Mouse),
+                        ReplayEvent::Undo,
+                        ReplayEvent::PickUp(DragSource::Keyboard, (10.0, -20.0)),
+                    ],
+                },
+            ],
+        };
+
+        assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let text = r#"{"version":999,"level":{"shapes":1,"level_type":"Tutorial"},"date":"2023-01-05","layout":[],"frames":[]}"#;
+        assert!(Replay::decode(text).is_err());
+    }
+
+    #[test]
+    fn test_unknown_challenge_generator_is_rejected() {
+        let text = r#"{"version":1,"level":{"shapes":10,"level_type":"Challenge"},"date":"2023-01-05","challenge_generator":999,"layout":[],"frames":[]}"#;
+        assert!(Replay::decode(text).is_err());
+    }
+}
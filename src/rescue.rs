@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use bevy_tweening::lens::*;
+use bevy_tweening::*;
+
+use crate::shape_maker::{get_shape_spawn_position, MAX_SHAPES, SHAPE_SIZE};
+use crate::*;
+
+pub struct RescuePlugin;
+
+impl Plugin for RescuePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(rescue_escaped_shapes.after(keep_shapes_inside))
+            .add_system(remove_rescue_effects);
+    }
+}
+
+const RESCUE_EFFECT_SECONDS: f64 = 0.75;
+const RESCUE_COLOR: Color = Color::ORANGE;
+
+/// A sensor outside the walls which catches shapes that have got past them
+#[derive(Component)]
+pub struct RescueSensor;
+
+#[derive(Component)]
+struct RescueEffect {
+    remove_at: f64,
+}
+
+/// The first spawn slot with no shape on it
+pub fn free_spawn_position(occupied: &[Vec2]) -> Vec2 {
+    (0..MAX_SHAPES)
+        .map(get_shape_spawn_position)
+        .find(|slot| occupied.iter().all(|x| x.distance(*slot) >= SHAPE_SIZE))
+        .unwrap_or_else(|| get_shape_spawn_position(0))
+}
+
+/// Move shapes touching a rescue sensor, or outside the play area, back into the play area
+fn rescue_escaped_shapes(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    sensors: Query<Entity, With<RescueSensor>>,
+    mut shapes: Query<(Entity, &mut Transform, &mut Velocity, &Draggable)>,
+    play_area: Res<PlayArea>,
+    mut drag_end_events: EventWriter<DragEndEvent>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    let mut escaped: Vec<Entity> = sensors
+        .iter()
+        .flat_map(|sensor| {
+            rapier_context
+                .intersections_with(sensor)
+                .filter(|(_, _, intersecting)| *intersecting)
+                .map(move |(a, b, _)| if a == sensor { b } else { a })
+        })
+        .filter(|entity| shapes.contains(*entity))
+        .collect();
+    // Shapes can tunnel past the sensors too
+    escaped.extend(
+        shapes
+            .iter()
+            .filter(|x| !play_area.contains(x.1.translation.truncate()))
+            .map(|x| x.0),
+    );
+    if escaped.is_empty() {
+        return;
+    }
+    escaped.sort();
+    escaped.dedup(); // A shape in a corner touches two sensors, and may also be outside
+
+    let now = time.elapsed_seconds_f64();
+    for entity in escaped.iter() {
+        let occupied: Vec<Vec2> = shapes
+            .iter()
+            .filter(|x| x.0 != *entity)
+            .map(|x| x.1.translation.truncate())
+            .collect();
+        let position = free_spawn_position(&occupied);
+
+        let Ok((_, mut transform, mut velocity, draggable)) = shapes.get_mut(*entity) else {continue;};
+        info!("Rescued shape at {}", transform.translation);
+
+        transform.translation = position.extend(transform.translation.z);
+        *velocity = Velocity::zero();
+        // End the drag properly so the input that was dragging it is released too
+        if let Draggable::Dragged(dragged) = draggable {
+            drag_end_events.send(DragEndEvent {
+                drag_source: dragged.drag_source,
+            });
+        }
+
+        spawn_rescue_effect(&mut commands, position, now, settings.reduced_motion);
+    }
+
+    SavedData::update(&mut pkv, |mut x| {
+        x.stats.shapes_rescued += escaped.len();
+        x
+    });
+}
+
+/// A ring around the place the shape was moved to
+fn spawn_rescue_effect(commands: &mut Commands, position: Vec2, now: f64, reduced_motion: bool) {
+    let mut entity = commands.spawn(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: SHAPE_SIZE,
+            center: Vec2::ZERO,
+        },
+        DrawMode::Stroke(StrokeMode::new(RESCUE_COLOR, 3.0)),
+        Transform::from_translation(position.extend(3.0)),
+    ));
+    entity.insert(RescueEffect {
+        remove_at: now + RESCUE_EFFECT_SECONDS,
+    });
+
+    if !reduced_motion {
+        entity.insert(Animator::new(Tween::new(
+            EaseFunction::QuadraticOut,
+            Duration::from_secs_f64(RESCUE_EFFECT_SECONDS),
+            TransformScaleLens {
+                start: Vec3::splat(2.0),
+                end: Vec3::splat(0.5),
+            },
+        )));
+    }
+}
+
+fn remove_rescue_effects(
+    mut commands: Commands,
+    effects: Query<(Entity, &RescueEffect)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, effect) in effects.iter() {
+        if effect.remove_at <= now {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_spawn_position_skips_occupied_slots() {
+        let occupied = vec![get_shape_spawn_position(0), get_shape_spawn_position(1)];
+
+        assert_eq!(free_spawn_position(&occupied), get_shape_spawn_position(2));
+        assert_eq!(free_spawn_position(&[]), get_shape_spawn_position(0));
+    }
+}
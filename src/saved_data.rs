@@ -1,45 +1,196 @@
-use bevy::prelude::ResMut;
-use bevy_pkv::PkvStore;
+use bevy::prelude::{error, ResMut};
+use bevy_pkv::{GetError, PkvStore};
 use chrono::NaiveDate;
-use serde::*;
+use serde::{de::DeserializeOwned, *};
+use std::collections::BTreeSet;
 
-use crate::get_today_date;
+use crate::{get_today_date, InfiniteProgress, SpeedrunBests, Stats};
 
-#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+const USER_KEY: &str = "user";
 
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SavedData {
     pub tutorial_finished: bool,
+    /// The streak when the challenge was last beaten.
+    /// Only used to seed `ChallengeHistory`, which streaks should be read from.
     pub challenge_streak: usize,
     pub last_challenge: Option<NaiveDate>,
+    /// The furthest campaign level the player has reached
+    pub campaign_level: usize,
+    pub stats: Stats,
+    pub infinite: Option<InfiniteProgress>,
+    pub speedrun: SpeedrunBests,
+    /// The ids of unlocked achievements
+    pub achievements: BTreeSet<String>,
+}
+
+/// Everything stored under the user key is wrapped in this so old formats can be migrated.
+///
+/// When `SavedData` changes in a way serde defaults can't handle,
+/// copy the current struct to a frozen `SavedDataV{n}`, point the old variant at it,
+/// add a new variant for `SavedData` and add a migration from the old version.
+#[derive(Debug, Serialize, Deserialize)]
+enum VersionedSavedData {
+    V1(SavedData),
+}
+
+impl VersionedSavedData {
+    fn migrate(self) -> SavedData {
+        match self {
+            VersionedSavedData::V1(data) => data,
+        }
+    }
+}
+
+/// The format used before saved data was versioned
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SavedDataV0 {
+    tutorial_finished: bool,
+    challenge_streak: usize,
+    last_challenge: Option<NaiveDate>,
+}
+
+impl From<SavedDataV0> for SavedData {
+    fn from(value: SavedDataV0) -> Self {
+        Self {
+            tutorial_finished: value.tutorial_finished,
+            challenge_streak: value.challenge_streak,
+            last_challenge: value.last_challenge,
+            ..Default::default()
+        }
+    }
+}
+
+/// Somewhere saved data can be read from
+pub trait SaveSource {
+    fn load<T: DeserializeOwned>(&self) -> Option<T>;
+    /// Whether anything is stored, even if it can't be read
+    fn has_data(&self) -> bool;
+}
+
+/// Whatever is stored under a key
+pub struct StoredValue<'a>(pub &'a PkvStore, pub &'a str);
+
+impl SaveSource for StoredValue<'_> {
+    fn load<T: DeserializeOwned>(&self) -> Option<T> {
+        self.0.get::<T>(self.1).ok()
+    }
+
+    fn has_data(&self) -> bool {
+        !matches!(
+            self.0.get::<serde_json::Value>(self.1),
+            Err(GetError::NotFound)
+        )
+    }
+}
+
+impl SaveSource for serde_json::Value {
+    fn load<T: DeserializeOwned>(&self) -> Option<T> {
+        T::deserialize(self).ok()
+    }
+
+    fn has_data(&self) -> bool {
+        !self.is_null()
+    }
 }
 
 impl SavedData {
+    /// Load the saved data in any known format, migrating it to the current version
+    fn load(source: &impl SaveSource) -> Option<Self> {
+        if let Some(versioned) = source.load::<VersionedSavedData>() {
+            Some(versioned.migrate())
+        } else {
+            source.load::<SavedDataV0>().map(|x| x.into())
+        }
+    }
+
+    /// Load the saved data, or the default if nothing has been saved.
+    /// `None` if there is saved data which can't be read, e.g. from a newer version of the game.
+    /// That data must not be overwritten.
+    fn load_or_default(source: &impl SaveSource) -> Option<Self> {
+        match Self::load(source) {
+            Some(data) => Some(data),
+            None if source.has_data() => None,
+            None => Some(Self::default()),
+        }
+    }
+
+    fn save(&self, pkv: &mut PkvStore) {
+        pkv.set(USER_KEY, &VersionedSavedData::V1(self.clone()))
+            .expect("failed to store user");
+    }
+
+    pub fn to_versioned_json(&self) -> serde_json::Value {
+        serde_json::to_value(VersionedSavedData::V1(self.clone()))
+            .expect("Could not serialize saved data")
+    }
+
+    pub fn from_versioned_json(value: &serde_json::Value) -> Option<Self> {
+        Self::load(value)
+    }
+
+    /// Combine with saved data from another device, keeping the best progress from each
+    #[must_use]
+    pub fn merge(self, other: SavedData) -> SavedData {
+        let (challenge_streak, last_challenge) = if (other.last_challenge, other.challenge_streak)
+            > (self.last_challenge, self.challenge_streak)
+        {
+            (other.challenge_streak, other.last_challenge)
+        } else {
+            (self.challenge_streak, self.last_challenge)
+        };
+
+        let infinite = match (self.infinite, other.infinite) {
+            (Some(a), Some(b)) => Some(if b.shapes > a.shapes { b } else { a }),
+            (a, b) => a.or(b),
+        };
+
+        SavedData {
+            tutorial_finished: self.tutorial_finished || other.tutorial_finished,
+            challenge_streak,
+            last_challenge,
+            campaign_level: self.campaign_level.max(other.campaign_level),
+            stats: self.stats.merge(&other.stats),
+            infinite,
+            speedrun: self.speedrun.merge(&other.speedrun),
+            achievements: self.achievements.union(&other.achievements).cloned().collect(),
+        }
+    }
+
+    /// The stored data, or the default if it can't be read. Nothing is written.
+    pub fn read(pkv: &PkvStore) -> Self {
+        Self::load(&StoredValue(pkv, USER_KEY)).unwrap_or_default()
+    }
+
     pub fn get_or_create(pkv: &mut ResMut<PkvStore>) -> Self {
-        if let Ok(user) = pkv.get::<SavedData>("user") {
+        let source = StoredValue(&**pkv, USER_KEY);
+        if let Some(user) = Self::load(&source) {
             user
+        } else if source.has_data() {
+            error!("Saved data could not be read so it will not be overwritten");
+            SavedData::default()
         } else {
             let user = SavedData::default();
-            pkv.set("user", &user).expect("failed to store user");
+            user.save(pkv);
             user
         }
     }
 
-    pub fn with_todays_challenge_beat(&self) -> Self {
+    /// Record today's challenge as beaten, with the streak from `ChallengeHistory`
+    pub fn with_todays_challenge_beat(&self, challenge_streak: usize) -> Self {
         let today = get_today_date();
 
-        if let Some(previous) = self.last_challenge {
-            if previous.checked_add_days(chrono::Days::new(1)) == Some(today) {
-                return Self {
-                    tutorial_finished: true,
-                    challenge_streak: self.challenge_streak + 1,
-                    last_challenge: Some(today),
-                };
-            }
-        }
+        let mut stats = self.stats.clone();
+        stats.longest_streak = stats.longest_streak.max(challenge_streak);
+
         Self {
             tutorial_finished: true,
-            challenge_streak: 1,
+            challenge_streak,
             last_challenge: Some(today),
+            stats,
+            ..self.clone()
         }
     }
 
@@ -47,15 +198,13 @@ impl SavedData {
         pkv: &mut ResMut<PkvStore>,
         f: F,
     ) -> SavedData {
-        let updated_user = if let Ok(user) = pkv.get::<SavedData>("user") {
-            f(user)
-        } else {
-            let user = SavedData::default();
-            f(user)
+        let Some(user) = Self::load_or_default(&StoredValue(&**pkv, USER_KEY)) else {
+            error!("Saved data could not be read so it will not be overwritten");
+            return f(SavedData::default());
         };
 
-        pkv.set("user", &updated_user)
-            .expect("failed to store user");
+        let updated_user = f(user);
+        updated_user.save(pkv);
         updated_user
     }
 
@@ -68,3 +217,91 @@ impl SavedData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    struct JsonSource<'a>(&'a str);
+
+    impl SaveSource for JsonSource<'_> {
+        fn load<T: DeserializeOwned>(&self) -> Option<T> {
+            serde_json::from_str(self.0).ok()
+        }
+
+        fn has_data(&self) -> bool {
+            !self.0.is_empty()
+        }
+    }
+
+    #[test_case(r#"{"tutorial_finished":true,"challenge_streak":3,"last_challenge":"2023-01-05"}"# ; "v0 original")]
+    #[test_case(r#"{"V1":{"tutorial_finished":true,"challenge_streak":3,"last_challenge":"2023-01-05"}}"# ; "v1")]
+    fn test_load_historical_format(json: &str) {
+        let data = SavedData::load(&JsonSource(json)).expect("Could not load saved data");
+
+        assert!(data.tutorial_finished);
+        assert_eq!(data.challenge_streak, 3);
+        assert_eq!(data.last_challenge, NaiveDate::from_ymd_opt(2023, 1, 5));
+    }
+
+    #[test]
+    fn test_unknown_version_is_not_loaded() {
+        let data = SavedData::load(&JsonSource(r#"{"V999":{"tutorial_finished":true}}"#));
+        assert_eq!(data, None);
+    }
+
+    #[test]
+    fn test_unreadable_data_is_not_replaced_with_default() {
+        let newer = JsonSource(r#"{"V999":{"tutorial_finished":true}}"#);
+        assert_eq!(SavedData::load_or_default(&newer), None);
+
+        let corrupt = JsonSource(r#"{"V1":"#);
+        assert_eq!(SavedData::load_or_default(&corrupt), None);
+
+        let missing = JsonSource("");
+        assert_eq!(
+            SavedData::load_or_default(&missing),
+            Some(SavedData::default())
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let old = SavedData {
+            challenge_streak: 10,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 1, 5),
+            campaign_level: 4,
+            ..Default::default()
+        };
+        let new = SavedData {
+            tutorial_finished: true,
+            challenge_streak: 2,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 2, 1),
+            campaign_level: 1,
+            ..Default::default()
+        };
+
+        let merged = old.clone().merge(new.clone());
+
+        assert!(merged.tutorial_finished);
+        assert_eq!(merged.challenge_streak, 2);
+        assert_eq!(merged.last_challenge, new.last_challenge);
+        assert_eq!(merged.campaign_level, 4);
+        assert_eq!(merged, new.merge(old));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = SavedData {
+            tutorial_finished: true,
+            challenge_streak: 4,
+            last_challenge: NaiveDate::from_ymd_opt(2023, 2, 1),
+            campaign_level: 3,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&VersionedSavedData::V1(data.clone())).unwrap();
+
+        assert_eq!(SavedData::load(&JsonSource(&json)), Some(data));
+    }
+}
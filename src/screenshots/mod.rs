@@ -2,8 +2,10 @@ use std::{fs, ops::Neg};
 
 use anyhow::anyhow;
 use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
 use bevy_prototype_lyon::prelude::{tess::geom::traits::Transformation, *};
 use resvg::usvg::{self, NodeExt};
+use serde::*;
 
 use crate::*;
 
@@ -14,6 +16,7 @@ impl Plugin for ScreenshotPlugin {
         app.insert_resource(SavedSvg::default())
             .add_event::<SaveSVGEvent>()
             .add_event::<DownloadPngEvent>()
+            .add_event::<SvgSavedEvent>()
             .add_system_to_stage(CoreStage::Last, save_svg)
             .add_system(download_svg);
     }
@@ -21,13 +24,21 @@ impl Plugin for ScreenshotPlugin {
 
 pub struct SaveSVGEvent {
     pub title: String,
+    pub level: GameLevel,
 }
 
 pub struct DownloadPngEvent;
 
+/// Sent after the picture of a completed level has been created
+pub struct SvgSavedEvent {
+    pub file: SvgFile,
+    pub level: GameLevel,
+}
+
 #[derive(Resource, Default)]
 pub struct SavedSvg(Option<SvgFile>);
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SvgFile {
     pub title: String,
     pub svg: String,
@@ -36,30 +47,37 @@ pub struct SvgFile {
 fn download_svg(mut events: EventReader<DownloadPngEvent>, saves: Res<SavedSvg>) {
     for _event in events.iter() {
         if let Some(svg) = &saves.0 {
-            match string_to_png(&svg.svg) {
-                Ok(vec) => {
-                    let filename = svg.title.clone() + ".png";
-                    info!("downloading {filename}");
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        crate::wasm::download::download_bytes(filename.into(), vec);
-                    }
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        save_file(filename.into(), vec).expect("Could not save file");
-                    }
-                    println!("{}", svg.svg)
-                }
-                Err(err) => {
-                    error!("{}", err)
-                }
-            }
+            download_png(svg);
         } else {
             warn!("No Svg to save")
         }
     }
 }
 
+pub fn download_png(svg: &SvgFile) {
+    match string_to_png(&svg.svg) {
+        Ok(vec) => {
+            let filename = svg.title.clone() + ".png";
+            info!("downloading {filename}");
+            #[cfg(target_arch = "wasm32")]
+            {
+                crate::wasm::download::download_bytes(filename.into(), vec, "image/png");
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let path = desktop_file_path(&filename);
+                match save_file(path.clone(), vec) {
+                    Ok(()) => info!("Saved {}", path.display()),
+                    Err(err) => error!("Could not save {}: {}", path.display(), err),
+                }
+            }
+        }
+        Err(err) => {
+            error!("{}", err)
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn save_file(file_name: std::path::PathBuf, bytes: Vec<u8>) -> anyhow::Result<()> {
     fs::write(file_name, bytes)?;
@@ -73,18 +91,51 @@ fn save_svg(
         (&Transform, &Path, &DrawMode),
         (With<Draggable>, Without<Wall>, Without<Padlock>),
     >,
+    joints: Query<(&Transform, &Path, &DrawMode), With<JointConnector>>,
     mut saves: ResMut<SavedSvg>,
+    mut saved_events: EventWriter<SvgSavedEvent>,
 ) {
     for event in events.iter() {
-        let svg = create_svg(query.iter());
-        *saves = SavedSvg(Some(SvgFile {
+        // Joints are drawn after the shapes so they are on top
+        let svg = create_svg(query.iter().chain(joints.iter()));
+        let file = SvgFile {
             title: event.title.clone(),
             svg,
-        }))
+        };
+        saved_events.send(SvgSavedEvent {
+            file: file.clone(),
+            level: event.level,
+        });
+        *saves = SavedSvg(Some(file))
     }
 }
 
 fn string_to_png(str: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let vec = render_svg(str, 1.0)?.encode_png()?;
+    Ok(vec)
+}
+
+/// Render an svg as an image which fits in a square with sides of `max_size`
+pub fn string_to_image(str: &str, max_size: f32) -> Result<Image, anyhow::Error> {
+    let tree = usvg::Tree::from_str(str, &usvg::Options::default())?;
+    let bounding_box = tree.root.calculate_bbox().ok_or(anyhow!("Svg is empty"))?;
+    let zoom = max_size / bounding_box.width().max(bounding_box.height()) as f32;
+
+    let pixmap = render_svg(str, zoom)?;
+
+    Ok(Image::new(
+        Extent3d {
+            width: pixmap.width(),
+            height: pixmap.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixmap.take(),
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+}
+
+fn render_svg(str: &str, zoom: f32) -> Result<resvg::tiny_skia::Pixmap, anyhow::Error> {
     //println!("{}", str);
     let opt = usvg::Options::default();
     //info!(str);
@@ -92,27 +143,28 @@ fn string_to_png(str: &str) -> Result<Vec<u8>, anyhow::Error> {
     //info!("Tree Size {:?}", tree.size);
     //info!("Viewbox {:?}", tree.view_box);
     //info!("ViewBox Size {:?}", tree.view_box.rect.size());
-    let bounding_box = tree.root.calculate_bbox().unwrap();
+    let bounding_box = tree.root.calculate_bbox().ok_or(anyhow!("Svg is empty"))?;
 
-    let pixmap_size = bounding_box.to_rect().unwrap().size().to_screen_size(); // tree.size.to_screen_size();
-                                                                               //info!("Pixmap size {:?}", pixmap_size);
-    let mut pixmap = resvg::tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height())
+    let rect = bounding_box.to_rect().ok_or(anyhow!("Svg has no area"))?; // tree.size.to_screen_size();
+    let width = (rect.width() * zoom as f64).ceil() as u32;
+    let height = (rect.height() * zoom as f64).ceil() as u32;
+    //info!("Pixmap size {:?}", pixmap_size);
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
         .ok_or(anyhow!("Could not create pixmap"))?;
 
         pixmap.fill(resvg::tiny_skia::Color::from_rgba(BACKGROUND_COLOR.r(), BACKGROUND_COLOR.g(), BACKGROUND_COLOR.b(), BACKGROUND_COLOR.a()).unwrap());
     resvg::render(
         &tree,
-        usvg::FitTo::Original,
+        usvg::FitTo::Zoom(zoom),
         resvg::tiny_skia::Transform::from_translate(
-            bounding_box.x().neg() as f32,
-            bounding_box.y().neg() as f32,
+            bounding_box.x().neg() as f32 * zoom,
+            bounding_box.y().neg() as f32 * zoom,
         ),
         pixmap.as_mut(),
     )
     .ok_or(anyhow!("Could not render svg"))?;
 
-    let vec = pixmap.encode_png()?;
-    Ok(vec)
+    Ok(pixmap)
 }
 
 pub fn create_svg<'a, I: Iterator<Item = (&'a Transform, &'a Path, &'a DrawMode)>>(
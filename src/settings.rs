@@ -0,0 +1,267 @@
+use serde::*;
+use strum::IntoEnumIterator;
+
+use crate::shape_maker::ShapeIndex;
+use crate::*;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .add_event::<ShowSettingsEvent>()
+            .add_startup_system(load_settings)
+            .add_system(show_settings)
+            .add_system(setting_button_system)
+            .add_system(update_setting_texts.after(setting_button_system))
+            .add_system_to_stage(CoreStage::PostUpdate, apply_palette);
+    }
+}
+
+const SETTINGS_KEY: &str = "settings";
+/// The number of rotation steps in a full turn
+const SNAP_DIVISIONS: [u32; 5] = [8, 12, 16, 24, 72];
+const ZOOM_MAGNIFICATIONS: [f32; 3] = [2.0, 3.0, 4.0];
+
+pub struct ShowSettingsEvent;
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub snap_divisions: u32,
+    /// Show a magnified view of shapes dragged by touch
+    pub zoom_lens: bool,
+    pub zoom_magnification: f32,
+    /// Fade out the text shown at the start of a level
+    pub fade_text: bool,
+    pub palette: Palette,
+    pub reduced_motion: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            snap_divisions: 16,
+            zoom_lens: true,
+            zoom_magnification: 3.0,
+            fade_text: true,
+            palette: Palette::Default,
+            reduced_motion: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn snap_resolution(&self) -> f32 {
+        std::f32::consts::TAU / self.snap_divisions.max(1) as f32
+    }
+
+    pub fn zoom_scale(&self) -> f32 {
+        1.0 / self.zoom_magnification
+    }
+
+    fn save(&self, pkv: &mut PkvStore) {
+        if let Err(err) = pkv.set(SETTINGS_KEY, self) {
+            error!("Could not save settings: {}", err);
+        }
+    }
+
+    /// Change a setting to its next value
+    fn cycle(&mut self, row: SettingRow) {
+        match row {
+            SettingRow::Snap => self.snap_divisions = next(&SNAP_DIVISIONS, self.snap_divisions),
+            SettingRow::ZoomLens => self.zoom_lens = !self.zoom_lens,
+            SettingRow::ZoomMagnification => {
+                self.zoom_magnification = next(&ZOOM_MAGNIFICATIONS, self.zoom_magnification)
+            }
+            SettingRow::TextFade => self.fade_text = !self.fade_text,
+            SettingRow::Palette => {
+                let palettes: Vec<_> = Palette::iter().collect();
+                self.palette = next(&palettes, self.palette)
+            }
+            SettingRow::ReducedMotion => self.reduced_motion = !self.reduced_motion,
+            SettingRow::ResetDefaults => *self = Settings::default(),
+        }
+    }
+}
+
+/// The option after `current`, or the first option if `current` is not one of them
+fn next<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let index = options
+        .iter()
+        .position(|x| *x == current)
+        .map(|i| (i + 1) % options.len())
+        .unwrap_or_default();
+    options[index]
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+enum SettingRow {
+    Snap,
+    ZoomLens,
+    ZoomMagnification,
+    TextFade,
+    Palette,
+    ReducedMotion,
+    ResetDefaults,
+}
+
+impl SettingRow {
+    const ALL: [SettingRow; 7] = [
+        SettingRow::Snap,
+        SettingRow::ZoomLens,
+        SettingRow::ZoomMagnification,
+        SettingRow::TextFade,
+        SettingRow::Palette,
+        SettingRow::ReducedMotion,
+        SettingRow::ResetDefaults,
+    ];
+
+    fn text(&self, settings: &Settings) -> String {
+        match self {
+            SettingRow::Snap => format!(
+                "Rotation snap: {}°",
+                360.0 / settings.snap_divisions.max(1) as f32
+            ),
+            SettingRow::ZoomLens => format!("Zoom lens: {}", on_off(settings.zoom_lens)),
+            SettingRow::ZoomMagnification => {
+                format!("Zoom magnification: {}x", settings.zoom_magnification)
+            }
+            SettingRow::TextFade => format!("Fade level text: {}", on_off(settings.fade_text)),
+            SettingRow::Palette => format!("Colours: {}", settings.palette.name()),
+            SettingRow::ReducedMotion => {
+                format!("Reduced motion: {}", on_off(settings.reduced_motion))
+            }
+            SettingRow::ResetDefaults => "Reset to defaults".to_string(),
+        }
+    }
+}
+
+/// Marks the text of a row on the settings screen
+#[derive(Component)]
+struct SettingText(SettingRow);
+
+fn load_settings(mut settings: ResMut<Settings>, pkv: Res<PkvStore>) {
+    *settings = pkv.get::<Settings>(SETTINGS_KEY).unwrap_or_default();
+}
+
+fn show_settings(
+    mut commands: Commands,
+    mut events: EventReader<ShowSettingsEvent>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Settings",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        for row in SettingRow::ALL {
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(4.0)),
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..Default::default()
+                })
+                .insert(row)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            row.text(&settings),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 16.0,
+                                color: SMALL_TEXT_COLOR,
+                            },
+                        ))
+                        .insert(SettingText(row));
+                });
+        }
+    });
+}
+
+fn setting_button_system(
+    rows: Query<(&Interaction, &SettingRow), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction == Interaction::Clicked {
+            settings.cycle(*row);
+            settings.save(&mut pkv);
+        }
+    }
+}
+
+fn update_setting_texts(settings: Res<Settings>, mut texts: Query<(&mut Text, &SettingText)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, row) in texts.iter_mut() {
+        text.sections[0].value = row.0.text(&settings);
+    }
+}
+
+/// Colour new shapes, or all shapes when the palette changes
+fn apply_palette(
+    settings: Res<Settings>,
+    mut shapes: Query<(&ShapeIndex, &mut DrawMode)>,
+    added: Query<Entity, Added<ShapeIndex>>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+    for (index, mut draw_mode) in shapes.iter_mut() {
+        *draw_mode = DrawMode::Fill(FillMode::color(settings.palette.choose_color(index.0)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_snap_is_sixteenth_of_a_turn() {
+        assert_eq!(
+            Settings::default().snap_resolution(),
+            std::f32::consts::TAU / 16.0
+        );
+    }
+
+    #[test]
+    fn test_cycle_wraps_around() {
+        let mut settings = Settings::default();
+        for _ in 0..SNAP_DIVISIONS.len() {
+            settings.cycle(SettingRow::Snap);
+        }
+        assert_eq!(settings.snap_divisions, 16);
+
+        settings.snap_divisions = 5; // Not one of the options
+        settings.cycle(SettingRow::Snap);
+        assert_eq!(settings.snap_divisions, SNAP_DIVISIONS[0]);
+    }
+}
@@ -1,6 +1,5 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use chrono::Datelike;
 use itertools::Itertools;
 
 use crate::{game_shape::GameShape, *};
@@ -10,12 +9,48 @@ use rand::{rngs::StdRng, seq::SliceRandom, Rng};
 pub const SHAPE_SIZE: f32 = 50f32;
 pub const MAX_SHAPES: usize = 36;
 
-pub fn create_level_shapes(commands: &mut Commands, level: GameLevel) {
+pub fn create_level_shapes(
+    commands: &mut Commands,
+    level: GameLevel,
+    campaign: &Campaign,
+    saved_layout: Option<Vec<SavedShape>>,
+) {
+    if let Some(layout) = saved_layout {
+        let shapes = create_saved_shapes(commands, layout);
+        if let LevelType::Campaign(index) = level.level_type {
+            if let Some(campaign_level) = campaign.get_level(index) {
+                create_campaign_joints(commands, campaign_level, &shapes);
+            }
+        }
+        return;
+    }
+
     let mut position_rng = rand::thread_rng();
 
     let mut positions = (0..MAX_SHAPES).collect_vec();
     positions.shuffle(&mut position_rng);
 
+    if let LevelType::Campaign(index) = level.level_type {
+        if let Some(campaign_level) = campaign.get_level(index) {
+            let shapes = create_campaign_shapes(commands, campaign_level, &positions);
+            create_campaign_joints(commands, campaign_level, &shapes);
+        }
+        return;
+    }
+
+    match level.level_type {
+        LevelType::Challenge => {
+            let layout = challenge::challenge_layout(get_today_date(), level.shapes);
+            create_layout_shapes(commands, layout);
+            return;
+        }
+        LevelType::Speedrun(index) => {
+            create_layout_shapes(commands, speedrun_layout(index));
+            return;
+        }
+        _ => {}
+    }
+
     let shapes: Vec<&'static GameShape> = match level.level_type {
         LevelType::Tutorial => match level.shapes {
             1 => vec![&game_shape::ALL_SHAPES[11]],
@@ -33,9 +68,9 @@ pub fn create_level_shapes(commands: &mut Commands, level: GameLevel) {
             ],
             _ => vec![&game_shape::ALL_SHAPES[0]],
         },
-        LevelType::Infinite => {
+        LevelType::Infinite { seed } => {
             let mut shapes: Vec<&'static GameShape> = vec![];
-            let mut shape_rng = rand::thread_rng();
+            let mut shape_rng: StdRng = rand::SeedableRng::seed_from_u64(seed);
             for _ in 0..level.shapes {
                 let shape = crate::game_shape::ALL_SHAPES
                     .choose(&mut shape_rng)
@@ -44,21 +79,10 @@ pub fn create_level_shapes(commands: &mut Commands, level: GameLevel) {
             }
             shapes
         }
-        LevelType::Challenge => {
-            let mut shapes: Vec<&'static GameShape> = vec![];
-
-            let today = get_today_date();
-            let seed = (today.year().unsigned_abs() * 2000) + (today.month() * 100) + today.day();
-            let mut shape_rng: StdRng = rand::SeedableRng::seed_from_u64(seed as u64);
-            for _ in 0..level.shapes {
-                let shape = crate::game_shape::ALL_SHAPES
-                    .choose(&mut shape_rng)
-                    .unwrap();
-                shapes.push(shape)
-            }
-            shapes
-        }
-        LevelType::ChallengeComplete(_) => vec![],
+        LevelType::Challenge
+        | LevelType::ChallengeComplete(_)
+        | LevelType::Campaign(_)
+        | LevelType::Speedrun(_) => vec![],
     };
 
     for (index, shape) in shapes.into_iter().enumerate() {
@@ -73,11 +97,105 @@ pub fn create_level_shapes(commands: &mut Commands, level: GameLevel) {
             get_shape_spawn_position(i),
             angle,
             shape.draw_mode(),
+            false,
         );
     }
 }
 
-fn get_shape_spawn_position(i: usize) -> Vec2 {
+fn create_campaign_shapes(
+    commands: &mut Commands,
+    campaign_level: &CampaignLevel,
+    positions: &[usize],
+) -> Vec<(Entity, Transform)> {
+    let mut position_rng = rand::thread_rng();
+    let mut positions = positions.iter();
+    let mut shapes = vec![];
+
+    for campaign_shape in campaign_level.shapes.iter() {
+        let Some(shape) = campaign_shape.shape.game_shape() else {continue;};
+
+        let position = match campaign_shape.position {
+            Some((x, y)) => Vec2::new(x, y),
+            None => get_shape_spawn_position(*positions.next().unwrap_or(&0)),
+        };
+        let angle = campaign_shape
+            .angle
+            .unwrap_or_else(|| position_rng.gen_range(0f32..std::f32::consts::TAU));
+
+        shapes.push(create_shape(
+            commands,
+            shape.clone(),
+            SHAPE_SIZE,
+            position,
+            angle,
+            shape.draw_mode(),
+            campaign_shape.locked,
+        ));
+    }
+    shapes
+}
+
+/// Join the shapes of a campaign level, which must be in the order they are in the level
+fn create_campaign_joints(
+    commands: &mut Commands,
+    campaign_level: &CampaignLevel,
+    shapes: &[(Entity, Transform)],
+) {
+    for joint in campaign_level.joints.iter() {
+        let (a, b) = joint.shapes;
+        if let (Some((parent, parent_transform)), Some((child, child_transform))) =
+            (shapes.get(a), shapes.get(b))
+        {
+            create_joint(
+                commands,
+                joint.kind,
+                (*parent, parent_transform),
+                (*child, child_transform),
+            );
+        }
+    }
+}
+
+/// Create shapes from a generated layout, so every player starts with the same board
+fn create_layout_shapes(commands: &mut Commands, layout: Vec<challenge::ChallengeShape>) {
+    for challenge_shape in layout {
+        let shape = challenge_shape.shape;
+
+        create_shape(
+            commands,
+            shape.clone(),
+            SHAPE_SIZE,
+            get_shape_spawn_position(challenge_shape.slot),
+            challenge_shape.angle(),
+            shape.draw_mode(),
+            false,
+        );
+    }
+}
+
+fn create_saved_shapes(
+    commands: &mut Commands,
+    layout: Vec<SavedShape>,
+) -> Vec<(Entity, Transform)> {
+    let mut shapes = vec![];
+    for saved_shape in layout {
+        let Some(shape) = game_shape::ALL_SHAPES.get(saved_shape.shape_index) else {continue;};
+        let (x, y) = saved_shape.position;
+
+        shapes.push(create_shape(
+            commands,
+            shape.clone(),
+            SHAPE_SIZE,
+            Vec2::new(x, y),
+            saved_shape.angle,
+            shape.draw_mode(),
+            saved_shape.locked,
+        ));
+    }
+    shapes
+}
+
+pub fn get_shape_spawn_position(i: usize) -> Vec2 {
     const COLS: usize = 6;
     let left = SHAPE_SIZE * (COLS as f32) / 2.;
     let x = ((i % COLS) as f32) * SHAPE_SIZE - left;
@@ -93,15 +211,16 @@ pub fn create_shape(
     position: Vec2,
     angle: f32,
     draw_mode: DrawMode,
-) {
+    locked: bool,
+) -> (Entity, Transform) {
     let collider_shape = game_shape.body.to_collider_shape(shape_size);
     let transform: Transform = Transform {
         translation: position.extend(0.0),
-        rotation: Quat::from_rotation_x(angle),
+        rotation: Quat::from_rotation_z(angle),
         scale: Vec3::ONE,
     };
 
-    commands
+    let entity = commands
         .spawn(game_shape.body.get_shape_bundle(shape_size, draw_mode))
         .insert(RigidBody::Dynamic)
         .insert(collider_shape)
@@ -111,7 +230,12 @@ pub fn create_shape(
         .insert(GravityScale::default())
         .insert(Velocity::default())
         .insert(Dominance::default())
-        .insert(crate::Draggable::Free {})
+        .insert(ShapeIndex(game_shape.index))
+        .insert(if locked {
+            crate::Draggable::Locked
+        } else {
+            crate::Draggable::Free
+        })
         .with_children(|x| {
             x.spawn(bevy::render::view::visibility::RenderLayers::layer(
                 ZOOM_ENTITY_LAYER,
@@ -120,5 +244,11 @@ pub fn create_shape(
                 shape_size,
                 DrawMode::Stroke(StrokeMode::new(Color::BLACK, 1.)),
             ));
-        });
+        })
+        .id();
+    (entity, transform)
 }
+
+/// The index of the shape in `ALL_SHAPES`
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeIndex(pub usize);
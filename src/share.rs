@@ -0,0 +1,167 @@
+use chrono::{Days, NaiveDate};
+
+use crate::challenge::CHALLENGE_GENERATOR_VERSION;
+use crate::*;
+
+pub struct SharePlugin;
+
+impl Plugin for SharePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShareEvent>().add_system(share_result);
+    }
+}
+
+const SHARE_URL: &str = "https://wainwrightmark.github.io/EquilibriumRust/";
+#[cfg(not(target_arch = "wasm32"))]
+const SHARE_FILE_NAME: &str = "equilibrium-share.txt";
+/// Longer rows than this would wrap in most messaging apps
+const MAX_ATTEMPT_SQUARES: u32 = 10;
+const STREAK_DAYS: u64 = 7;
+
+/// Share the result of today's challenge
+pub struct ShareEvent;
+
+/// Everything which goes into the shared text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeResult {
+    pub date: NaiveDate,
+    /// Players with different generators had different challenges on the same date
+    pub generator_version: u32,
+    pub streak: usize,
+    pub attempts: u32,
+    /// Missing for days from before times were recorded
+    pub seconds: Option<f64>,
+    /// Whether each of the last seven days was beaten, ending with `date`
+    pub recent_days: Vec<bool>,
+}
+
+impl ChallengeResult {
+    pub fn from_history(history: &ChallengeHistory, date: NaiveDate) -> Option<Self> {
+        let day = history.days.get(&date)?;
+        let recent_days = (0..STREAK_DAYS)
+            .rev()
+            .map(|offset| {
+                date.checked_sub_days(Days::new(offset))
+                    .map(|d| history.days.contains_key(&d))
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        Some(Self {
+            date,
+            generator_version: day.generator_version.unwrap_or(CHALLENGE_GENERATOR_VERSION),
+            streak: history.current_streak(date),
+            attempts: day.attempts.max(1),
+            seconds: day.seconds,
+            recent_days,
+        })
+    }
+
+    /// A short text grid which can be pasted anywhere
+    pub fn share_text(&self) -> String {
+        let shown_attempts = self.attempts.min(MAX_ATTEMPT_SQUARES);
+        let mut attempts_row = "🟥".repeat(shown_attempts as usize - 1);
+        if self.attempts > MAX_ATTEMPT_SQUARES {
+            attempts_row.push('…');
+        }
+        attempts_row.push('🟩');
+
+        let days_row: String = self
+            .recent_days
+            .iter()
+            .map(|&beaten| if beaten { '🟩' } else { '⬜' })
+            .collect();
+
+        let mut details = vec![format!("Attempts {}", self.attempts)];
+        if let Some(seconds) = self.seconds {
+            details.push(format!("Time {}", format_time(seconds)));
+        }
+        details.push(format!("Streak {}", self.streak));
+
+        format!(
+            "Equilibrium {date} v{version}\n{attempts_row}\n{days_row}\n{details}\n{SHARE_URL}",
+            date = self.date.format("%Y-%m-%d"),
+            version = self.generator_version,
+            details = details.join(" | "),
+        )
+    }
+}
+
+fn share_result(mut events: EventReader<ShareEvent>, pkv: Res<PkvStore>) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let history = ChallengeHistory::load(&pkv);
+    let Some(result) = ChallengeResult::from_history(&history, get_today_date()) else {
+        warn!("Today's challenge has not been beaten");
+        return;
+    };
+    let text = result.share_text();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::wasm::share::share_text(text);
+    }
+    // Desktop builds can't open a share sheet, so the text is written to a file to copy from
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = desktop_file_path(SHARE_FILE_NAME);
+        match std::fs::write(&path, &text) {
+            Ok(()) => info!("Saved result to {}:\n{text}", path.display()),
+            Err(err) => error!("Could not save result to {}: {}", path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_text() {
+        let result = ChallengeResult {
+            date: NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+            generator_version: 2,
+            streak: 3,
+            attempts: 2,
+            seconds: Some(83.4),
+            recent_days: vec![false, true, false, false, true, true, true],
+        };
+
+        assert_eq!(
+            result.share_text(),
+            "Equilibrium 2023-01-05 v2\n🟥🟩\n⬜🟩⬜⬜🟩🟩🟩\nAttempts 2 | Time 1:23.4 | Streak 3\nhttps://wainwrightmark.github.io/EquilibriumRust/"
+        );
+    }
+
+    #[test]
+    fn test_many_attempts_are_shortened() {
+        let result = ChallengeResult {
+            date: NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+            generator_version: 2,
+            streak: 1,
+            attempts: 25,
+            seconds: Some(10.0),
+            recent_days: vec![true],
+        };
+
+        assert!(result
+            .share_text()
+            .starts_with("Equilibrium 2023-01-05 v2\n🟥🟥🟥🟥🟥🟥🟥🟥🟥…🟩\n"));
+    }
+
+    #[test]
+    fn test_time_is_left_out_when_not_recorded() {
+        let result = ChallengeResult {
+            date: NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+            generator_version: 2,
+            streak: 4,
+            attempts: 1,
+            seconds: None,
+            recent_days: vec![true],
+        };
+
+        assert!(result.share_text().contains("\nAttempts 1 | Streak 4\n"));
+    }
+}
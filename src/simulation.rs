@@ -0,0 +1,264 @@
+// The headless api is currently only used by tests
+#![cfg_attr(not(test), allow(dead_code))]
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::crossbeam::atomic::AtomicCell;
+use bevy_rapier2d::rapier::prelude::{
+    BroadPhase, CCDSolver, ColliderBuilder, ColliderSet, EventHandler, ImpulseJointSet,
+    IntegrationParameters, IslandManager, MultibodyJointSet, NarrowPhase, PhysicsPipeline,
+    RigidBodyBuilder, RigidBodySet,
+};
+
+use crate::game_shape::GameShape;
+use crate::platforms::SimulatedPlatform;
+use crate::shape_maker::SHAPE_SIZE;
+use crate::walls::{wall_rectangles, PlayArea};
+use crate::{GRAVITY, PHYSICS_SCALE};
+
+/// The number of physics steps per simulated second
+const STEPS_PER_SECOND: f32 = 60.0;
+
+/// A rapier world which can be stepped without a running app
+pub struct PhysicsWorld {
+    pub islands: IslandManager,
+    pub broad_phase: BroadPhase,
+    pub narrow_phase: NarrowPhase,
+    pub bodies: RigidBodySet,
+    pub colliders: ColliderSet,
+    pub impulse_joints: ImpulseJointSet,
+    pub multibody_joints: MultibodyJointSet,
+    pub ccd_solver: CCDSolver,
+    pub integration_parameters: IntegrationParameters,
+    /// Platforms which are moved as the world is stepped
+    pub platforms: Vec<SimulatedPlatform>,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self {
+            islands: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            integration_parameters: IntegrationParameters::default(),
+            platforms: vec![],
+        }
+    }
+}
+
+impl PhysicsWorld {
+    /// Clone the world of a running app
+    pub fn from_context(context: &RapierContext) -> Self {
+        Self {
+            islands: context.islands.clone(),
+            broad_phase: context.broad_phase.clone(),
+            narrow_phase: context.narrow_phase.clone(),
+            bodies: context.bodies.clone(),
+            colliders: context.colliders.clone(),
+            impulse_joints: context.impulse_joints.clone(),
+            multibody_joints: context.multibody_joints.clone(),
+            ccd_solver: context.ccd_solver.clone(),
+            integration_parameters: context.integration_parameters,
+            platforms: vec![],
+        }
+    }
+
+    /// Step the world forward until a sensor is touched.
+    /// Returns the number of steps taken before the touch, or `None` if no sensor was touched.
+    /// The gravity should already be divided by the physics scale.
+    pub fn step_until_sensor_collision(&mut self, gravity: Vect, steps: usize) -> Option<usize> {
+        let mut pipeline = PhysicsPipeline::default();
+        let event_handler = SensorCollisionHandler::default();
+        let gravity = gravity.into();
+
+        for i in 0..steps {
+            let seconds = (i + 1) as f32 * self.integration_parameters.dt;
+            for platform in self.platforms.iter() {
+                platform.apply(&mut self.bodies, &mut self.impulse_joints, seconds);
+            }
+
+            pipeline.step(
+                &gravity,
+                &self.integration_parameters,
+                &mut self.islands,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                &mut self.ccd_solver,
+                &(),
+                &event_handler,
+            );
+
+            if event_handler.collisions_found.load() {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    fn add_wall(&mut self, position: Vec2, width: f32, height: f32) {
+        let position = position / PHYSICS_SCALE;
+        let half_extents = Vec2::new(width, height) * 0.5 / PHYSICS_SCALE;
+
+        let handle = self.bodies.insert(
+            RigidBodyBuilder::fixed()
+                .translation(position.into())
+                .build(),
+        );
+
+        self.colliders.insert_with_parent(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y).build(),
+            handle,
+            &mut self.bodies,
+        );
+        self.colliders.insert_with_parent(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+                .sensor(true)
+                .active_events(bevy_rapier2d::rapier::prelude::ActiveEvents::COLLISION_EVENTS)
+                .build(),
+            handle,
+            &mut self.bodies,
+        );
+    }
+
+    fn add_shape(&mut self, shape: &SimulatedShape) {
+        let mut collider = shape.shape.body.to_collider_shape(SHAPE_SIZE);
+        collider.set_scale(Vec2::ONE / PHYSICS_SCALE, 10);
+
+        let mut builder = RigidBodyBuilder::dynamic()
+            .translation((shape.position / PHYSICS_SCALE).into())
+            .rotation(shape.angle)
+            .ccd_enabled(true);
+
+        if shape.locked {
+            // Locked shapes behave as they do in game - held in place and ignoring gravity
+            builder = builder
+                .lock_translations()
+                .lock_rotations()
+                .gravity_scale(0.0)
+                .dominance_group(10);
+        }
+
+        let handle = self.bodies.insert(builder.build());
+        self.colliders.insert_with_parent(
+            ColliderBuilder::new(collider.raw.clone()).build(),
+            handle,
+            &mut self.bodies,
+        );
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulatedShape {
+    pub shape: GameShape,
+    pub position: Vec2,
+    pub angle: f32,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimulationOutcome {
+    /// Nothing touched a wall for the whole simulation
+    Stable,
+    /// Something touched a wall after this many seconds
+    TouchedWall { seconds: f32 },
+}
+
+impl SimulationOutcome {
+    pub fn is_stable(&self) -> bool {
+        matches!(self, SimulationOutcome::Stable)
+    }
+}
+
+/// Simulate a tower in a world with the standard walls, without needing a running app
+pub fn simulate_tower(shapes: &[SimulatedShape], seconds: f32) -> SimulationOutcome {
+    let mut world = PhysicsWorld::default();
+
+    for (position, width, height, _) in wall_rectangles(&PlayArea::default()) {
+        world.add_wall(position, width, height);
+    }
+
+    for shape in shapes {
+        world.add_shape(shape);
+    }
+
+    let steps = (seconds * STEPS_PER_SECOND).floor() as usize;
+    world.integration_parameters.dt = 1.0 / STEPS_PER_SECOND;
+
+    match world.step_until_sensor_collision(GRAVITY / PHYSICS_SCALE, steps) {
+        Some(step) => SimulationOutcome::TouchedWall {
+            seconds: (step as f32) / STEPS_PER_SECOND,
+        },
+        None => SimulationOutcome::Stable,
+    }
+}
+
+#[derive(Default, Debug)]
+struct SensorCollisionHandler {
+    pub collisions_found: AtomicCell<bool>,
+}
+
+impl EventHandler for SensorCollisionHandler {
+    fn handle_collision_event(
+        &self,
+        _bodies: &bevy_rapier2d::rapier::prelude::RigidBodySet,
+        colliders: &bevy_rapier2d::rapier::prelude::ColliderSet,
+        event: bevy_rapier2d::rapier::prelude::CollisionEvent,
+        _contact_pair: Option<&bevy_rapier2d::rapier::prelude::ContactPair>,
+    ) {
+        for c in [event.collider1(), event.collider2()] {
+            if let Some(collider) = colliders.get(c) {
+                if collider.is_sensor() {
+                    self.collisions_found.store(true);
+                }
+            }
+        }
+    }
+
+    fn handle_contact_force_event(
+        &self,
+        _dt: bevy_rapier2d::rapier::prelude::Real,
+        _bodies: &bevy_rapier2d::rapier::prelude::RigidBodySet,
+        _colliders: &bevy_rapier2d::rapier::prelude::ColliderSet,
+        _contact_pair: &bevy_rapier2d::rapier::prelude::ContactPair,
+        _total_force_magnitude: bevy_rapier2d::rapier::prelude::Real,
+    ) {
+        //Do nothing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::ShapeName;
+    use test_case::test_case;
+
+    fn o_tetromino(x: f32, y: f32, locked: bool) -> SimulatedShape {
+        SimulatedShape {
+            shape: ShapeName::Tetromino("O".to_string())
+                .game_shape()
+                .unwrap()
+                .clone(),
+            position: Vec2::new(x, y),
+            angle: 0.0,
+            locked,
+        }
+    }
+
+    #[test_case(vec![o_tetromino(0., 0., true)], true ; "single locked shape")]
+    #[test_case(vec![o_tetromino(0., 0., false)], false ; "single falling shape")]
+    #[test_case(vec![o_tetromino(0., 0., true), o_tetromino(0., SHAPE_SIZE, false)], true ; "stacked squares")]
+    #[test_case(vec![o_tetromino(0., 0., true), o_tetromino(SHAPE_SIZE * 0.9, SHAPE_SIZE, false)], false ; "overhanging square")]
+    fn test_simulate_tower(shapes: Vec<SimulatedShape>, expect_stable: bool) {
+        let outcome = simulate_tower(&shapes, 10.0);
+        assert_eq!(outcome.is_stable(), expect_stable, "{outcome:?}");
+    }
+}
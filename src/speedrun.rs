@@ -0,0 +1,325 @@
+use serde::*;
+
+use crate::challenge::{generate_layout, ChallengeRng, ChallengeShape};
+use crate::*;
+
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpeedrunTimer>()
+            .add_event::<SpeedrunSplitEvent>()
+            .add_startup_system(setup_timer_text)
+            .add_system(track_speedrun.after(check_for_win))
+            .add_system(update_timer_text.after(track_speedrun));
+    }
+}
+
+/// The number of shapes in each speedrun level
+pub const SPEEDRUN_SHAPES: [usize; 5] = [3, 4, 5, 6, 7];
+/// Every run uses the same boards so times can be compared
+const SPEEDRUN_SEED: u64 = 0x5EED;
+
+/// Sent when a speedrun level is won
+pub struct SpeedrunSplitEvent {
+    pub index: usize,
+    /// When the tower was finished, before the win countdown started
+    pub finished_at: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedrunBests {
+    /// The splits of the fastest complete run
+    pub best_run: Option<Vec<f64>>,
+    /// The fastest time for each level, from any run
+    pub best_splits: Vec<f64>,
+}
+
+impl SpeedrunBests {
+    pub fn best_total(&self) -> Option<f64> {
+        self.best_run.as_ref().map(|x| x.iter().sum())
+    }
+
+    /// Record a completed run. Returns whether it was a new best.
+    pub fn record_run(&mut self, splits: &[f64]) -> bool {
+        for (index, split) in splits.iter().enumerate() {
+            match self.best_splits.get_mut(index) {
+                Some(best) => *best = best.min(*split),
+                None => self.best_splits.push(*split),
+            }
+        }
+
+        let total: f64 = splits.iter().sum();
+        let is_best = self.best_total().map(|best| total < best).unwrap_or(true);
+        if is_best {
+            self.best_run = Some(splits.to_vec());
+        }
+        is_best
+    }
+
+    #[must_use]
+    pub fn merge(&self, other: &SpeedrunBests) -> SpeedrunBests {
+        let mut merged = self.clone();
+        if let Some(run) = &other.best_run {
+            merged.record_run(run);
+        }
+        for (index, split) in other.best_splits.iter().enumerate() {
+            match merged.best_splits.get_mut(index) {
+                Some(best) => *best = best.min(*split),
+                None => merged.best_splits.push(*split),
+            }
+        }
+        merged
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SpeedrunTimer {
+    /// The speedrun level being played
+    level: Option<usize>,
+    /// When the split for the current level started, which is when a shape is first picked up.
+    /// The win countdown and the time between levels are not counted.
+    level_started: Option<f64>,
+    splits: Vec<f64>,
+}
+
+impl SpeedrunTimer {
+    fn total(&self) -> f64 {
+        self.splits.iter().sum()
+    }
+
+    fn current_split(&self, now: f64) -> f64 {
+        self.level_started.map(|x| now - x).unwrap_or_default()
+    }
+
+    /// Finish the current split. The next one starts when a shape is picked up.
+    fn end_split(&mut self, finished_at: f64) {
+        let split = self
+            .level_started
+            .take()
+            .map(|started| finished_at - started)
+            .unwrap_or_default()
+            .max(0.0);
+        self.splits.push(split);
+    }
+}
+
+#[derive(Component)]
+struct SpeedrunTimerText;
+
+pub fn speedrun_layout(index: usize) -> Vec<ChallengeShape> {
+    let shapes = SPEEDRUN_SHAPES.get(index).copied().unwrap_or_default();
+    generate_layout(ChallengeRng::new(SPEEDRUN_SEED + index as u64), shapes)
+}
+
+pub fn format_time(seconds: f64) -> String {
+    let tenths = (seconds.max(0.0) * 10.0).round() as u64;
+    format!("{}:{:02}.{}", tenths / 600, (tenths / 10) % 60, tenths % 10)
+}
+
+fn track_speedrun(
+    mut commands: Commands,
+    mut timer: ResMut<SpeedrunTimer>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    draggables: Query<&Draggable>,
+    mut split_events: EventReader<SpeedrunSplitEvent>,
+    mut change_level_events: EventReader<ChangeLevelEvent>,
+    mut pkv: ResMut<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for event in split_events.iter() {
+        if timer.level != Some(event.index) || timer.splits.len() != event.index {
+            continue;
+        }
+
+        timer.end_split(event.finished_at);
+
+        if timer.splits.len() == SPEEDRUN_SHAPES.len() {
+            let mut is_best = false;
+            let saved_data = SavedData::update(&mut pkv, |mut x| {
+                is_best = x.speedrun.record_run(&timer.splits);
+                x
+            });
+            show_results(
+                &mut commands,
+                &panels,
+                &asset_server,
+                &timer.splits,
+                &saved_data.speedrun,
+                is_best,
+            );
+        }
+    }
+
+    if change_level_events
+        .iter()
+        .any(|x| matches!(x, ChangeLevelEvent::StartSpeedrun))
+    {
+        *timer = SpeedrunTimer::default();
+    }
+
+    if current_level.is_changed() {
+        let level = match current_level.0.level_type {
+            LevelType::Speedrun(index) => Some(index),
+            _ => None,
+        };
+        // Resetting the level or going on to the next one keeps the run going
+        if level.is_none() {
+            *timer = SpeedrunTimer::default();
+        }
+        timer.level = level;
+    }
+
+    let picked_up = draggables.iter().any(|x| x.is_dragged());
+    if picked_up && timer.level.is_some() && timer.level_started.is_none() {
+        timer.level_started = Some(now);
+    }
+}
+
+fn show_results(
+    commands: &mut Commands,
+    panels: &Query<Entity, With<Panel>>,
+    asset_server: &AssetServer,
+    splits: &[f64],
+    bests: &SpeedrunBests,
+    is_best: bool,
+) {
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let mut lines: Vec<String> = splits
+        .iter()
+        .enumerate()
+        .map(|(index, split)| {
+            let best = bests.best_splits.get(index).copied().unwrap_or(*split);
+            format!(
+                "Level {}: {} (best {})",
+                index + 1,
+                format_time(*split),
+                format_time(best)
+            )
+        })
+        .collect();
+
+    lines.push(format!("Total: {}", format_time(splits.iter().sum())));
+    if is_best {
+        lines.push("New personal best!".to_string());
+    } else if let Some(best) = bests.best_total() {
+        lines.push(format!("Personal best: {}", format_time(best)));
+    }
+
+    spawn_panel(commands, panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Speedrun Complete",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        for line in lines {
+            parent.spawn(TextBundle::from_section(
+                line,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    color: SMALL_TEXT_COLOR,
+                },
+            ));
+        }
+    });
+}
+
+fn setup_timer_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 20.0,
+                    color: SMALL_TEXT_COLOR,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        )
+        .insert(ZIndex::Global(6))
+        .insert(SpeedrunTimerText);
+}
+
+fn update_timer_text(
+    timer: Res<SpeedrunTimer>,
+    time: Res<Time>,
+    mut texts: Query<&mut Text, With<SpeedrunTimerText>>,
+) {
+    let value = if timer.level.is_some() {
+        let now = time.elapsed_seconds_f64();
+        format!(
+            "{}\n{}",
+            format_time(timer.current_split(now)),
+            format_time(timer.total() + timer.current_split(now))
+        )
+    } else {
+        String::new()
+    };
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0.0), "0:00.0");
+        assert_eq!(format_time(61.26), "1:01.3");
+        assert_eq!(format_time(600.0), "10:00.0");
+    }
+
+    #[test]
+    fn test_record_run() {
+        let mut bests = SpeedrunBests::default();
+
+        assert!(bests.record_run(&[10.0, 20.0]));
+        assert!(!bests.record_run(&[5.0, 30.0]));
+        assert!(bests.record_run(&[12.0, 10.0]));
+
+        assert_eq!(bests.best_run, Some(vec![12.0, 10.0]));
+        assert_eq!(bests.best_splits, vec![5.0, 10.0]);
+    }
+
+    #[test]
+    fn test_split_excludes_countdown() {
+        let mut timer = SpeedrunTimer {
+            level: Some(0),
+            level_started: Some(10.0),
+            splits: vec![],
+        };
+        let win_time = 25.0;
+        let total_countdown = 5.0;
+
+        timer.end_split(win_time - total_countdown);
+
+        assert_eq!(timer.splits, vec![10.0]);
+        assert_eq!(timer.level_started, None);
+        assert_eq!(timer.current_split(win_time + 3.0), 0.0);
+    }
+}
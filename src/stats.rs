@@ -0,0 +1,255 @@
+use serde::*;
+
+use crate::*;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowStatsEvent>()
+            .add_system(track_play_time)
+            .add_system(track_drags)
+            .add_system(track_rotations)
+            .add_system(show_stats);
+    }
+}
+
+/// Play time is saved whenever this many seconds have accumulated
+const PLAY_TIME_SAVE_SECONDS: f32 = 10.0;
+/// Longer frames are assumed to be the app sleeping and are not counted as play time
+const PLAY_TIME_MAX_DELTA: f32 = 1.0;
+
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub levels_completed: LevelsCompleted,
+    /// The most shapes in a completed infinite level
+    pub best_infinite_height: usize,
+    pub longest_streak: usize,
+    pub total_play_seconds: u64,
+    pub drags: usize,
+    pub rotations: usize,
+    /// Times a win countdown was cancelled
+    pub failed_win_attempts: usize,
+    /// Shapes brought back after escaping the walls
+    pub shapes_rescued: usize,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelsCompleted {
+    pub tutorial: usize,
+    pub infinite: usize,
+    pub challenge: usize,
+    pub campaign: usize,
+}
+
+impl Stats {
+    pub fn record_win(&mut self, level: &GameLevel) {
+        match level.level_type {
+            LevelType::Tutorial => self.levels_completed.tutorial += 1,
+            LevelType::Infinite { .. } => {
+                self.levels_completed.infinite += 1;
+                self.best_infinite_height = self.best_infinite_height.max(level.shapes);
+            }
+            LevelType::Challenge => self.levels_completed.challenge += 1,
+            LevelType::ChallengeComplete(_) | LevelType::Speedrun(_) => {}
+            LevelType::Campaign(_) => self.levels_completed.campaign += 1,
+        }
+    }
+
+    /// Combine stats from two devices. Takes the larger of each value so merging twice has no effect.
+    #[must_use]
+    pub fn merge(&self, other: &Stats) -> Stats {
+        Stats {
+            levels_completed: LevelsCompleted {
+                tutorial: self.levels_completed.tutorial.max(other.levels_completed.tutorial),
+                infinite: self.levels_completed.infinite.max(other.levels_completed.infinite),
+                challenge: self
+                    .levels_completed
+                    .challenge
+                    .max(other.levels_completed.challenge),
+                campaign: self.levels_completed.campaign.max(other.levels_completed.campaign),
+            },
+            best_infinite_height: self.best_infinite_height.max(other.best_infinite_height),
+            longest_streak: self.longest_streak.max(other.longest_streak),
+            total_play_seconds: self.total_play_seconds.max(other.total_play_seconds),
+            drags: self.drags.max(other.drags),
+            rotations: self.rotations.max(other.rotations),
+            failed_win_attempts: self.failed_win_attempts.max(other.failed_win_attempts),
+            shapes_rescued: self.shapes_rescued.max(other.shapes_rescued),
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let hours = self.total_play_seconds / 3600;
+        let minutes = (self.total_play_seconds / 60) % 60;
+
+        vec![
+            format!("Tutorial levels: {}", self.levels_completed.tutorial),
+            format!("Infinite levels: {}", self.levels_completed.infinite),
+            format!("Best infinite tower: {}", self.best_infinite_height),
+            format!("Challenges: {}", self.levels_completed.challenge),
+            format!("Longest streak: {}", self.longest_streak),
+            format!("Campaign levels: {}", self.levels_completed.campaign),
+            format!("Play time: {hours}h {minutes:02}m"),
+            format!("Shapes dragged: {}", self.drags),
+            format!("Rotations: {}", self.rotations),
+            format!("Failed attempts: {}", self.failed_win_attempts),
+            format!("Shapes rescued: {}", self.shapes_rescued),
+        ]
+    }
+}
+
+pub struct ShowStatsEvent;
+
+fn track_play_time(time: Res<Time>, mut unsaved: Local<f32>, mut pkv: ResMut<PkvStore>) {
+    let delta = time.delta_seconds();
+    if delta < PLAY_TIME_MAX_DELTA {
+        *unsaved += delta;
+    }
+
+    if *unsaved >= PLAY_TIME_SAVE_SECONDS {
+        let seconds = unsaved.floor();
+        *unsaved -= seconds;
+        SavedData::update(&mut pkv, |mut x| {
+            x.stats.total_play_seconds += seconds as u64;
+            x
+        });
+    }
+}
+
+fn track_drags(mut events: EventReader<DragEndedEvent>, mut pkv: ResMut<PkvStore>) {
+    let count = events.iter().count();
+    if count > 0 {
+        SavedData::update(&mut pkv, |mut x| {
+            x.stats.drags += count;
+            x
+        });
+    }
+}
+
+fn track_rotations(
+    mut events: EventReader<RotateEvent>,
+    touch_rotate: Res<TouchRotateResource>,
+    mut was_touch_rotating: Local<bool>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    // Touch rotation sends many small events so count each gesture once
+    let mut count = events
+        .iter()
+        .filter(|x| x.snap_resolution.is_some())
+        .count();
+
+    let is_touch_rotating = touch_rotate.is_rotating();
+    if is_touch_rotating && !*was_touch_rotating {
+        count += 1;
+    }
+    *was_touch_rotating = is_touch_rotating;
+
+    if count > 0 {
+        SavedData::update(&mut pkv, |mut x| {
+            x.stats.rotations += count;
+            x
+        });
+    }
+}
+
+fn show_stats(
+    mut commands: Commands,
+    mut events: EventReader<ShowStatsEvent>,
+    mut pkv: ResMut<PkvStore>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<Panel>>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    let stats = SavedData::get_or_create(&mut pkv).stats;
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    spawn_panel(&mut commands, &panels, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Statistics",
+            TextStyle {
+                font: font.clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        ));
+
+        for line in stats.lines() {
+            parent.spawn(TextBundle::from_section(
+                line,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    color: SMALL_TEXT_COLOR,
+                },
+            ));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(stats: &mut Stats, shapes: usize, level_type: LevelType) {
+        stats.record_win(&GameLevel { shapes, level_type });
+    }
+
+    #[test]
+    fn test_record_win() {
+        let mut stats = Stats::default();
+        win(&mut stats, 2, LevelType::Tutorial);
+        win(&mut stats, 7, LevelType::Infinite { seed: 1 });
+        win(&mut stats, 5, LevelType::Infinite { seed: 2 });
+        win(&mut stats, 10, LevelType::Challenge);
+        win(&mut stats, 11, LevelType::ChallengeComplete(1));
+        win(&mut stats, 4, LevelType::Campaign(0));
+        win(&mut stats, 3, LevelType::Speedrun(0));
+
+        assert_eq!(
+            stats.levels_completed,
+            LevelsCompleted {
+                tutorial: 1,
+                infinite: 2,
+                challenge: 1,
+                campaign: 1,
+            }
+        );
+        assert_eq!(stats.best_infinite_height, 7);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut first = Stats {
+            best_infinite_height: 9,
+            drags: 5,
+            shapes_rescued: 1,
+            ..Default::default()
+        };
+        first.levels_completed.infinite = 4;
+        let mut second = Stats {
+            best_infinite_height: 6,
+            drags: 8,
+            total_play_seconds: 60,
+            ..Default::default()
+        };
+        second.levels_completed.infinite = 3;
+        second.levels_completed.tutorial = 4;
+
+        let merged = first.merge(&second);
+
+        assert_eq!(merged, second.merge(&first));
+        assert_eq!(merged.merge(&second), merged);
+        assert_eq!(merged.best_infinite_height, 9);
+        assert_eq!(merged.drags, 8);
+        assert_eq!(merged.shapes_rescued, 1);
+        assert_eq!(merged.total_play_seconds, 60);
+        assert_eq!(merged.levels_completed.infinite, 4);
+        assert_eq!(merged.levels_completed.tutorial, 4);
+    }
+}
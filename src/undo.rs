@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use crate::*;
+
+pub struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UndoHistory>()
+            .add_event::<UndoEvent>()
+            .add_system_to_stage(CoreStage::First, clear_history.after(handle_change_level))
+            .add_system(
+                handle_undo
+                    .after(input::keyboard_listener)
+                    .before(handle_drag_changes),
+            );
+    }
+}
+
+/// The maximum number of snapshots kept in the history
+const MAX_UNDO: usize = 20;
+
+#[derive(Debug)]
+pub struct UndoEvent;
+
+#[derive(Debug, Clone)]
+pub struct ShapeSnapshot {
+    pub entity: Entity,
+    pub transform: Transform,
+    pub velocity: Velocity,
+    pub draggable: Draggable,
+}
+
+/// The state of every shape just before a shape was picked up
+#[derive(Debug, Clone, Default)]
+pub struct BoardSnapshot(pub Vec<ShapeSnapshot>);
+
+#[derive(Resource, Debug, Default)]
+pub struct UndoHistory(VecDeque<BoardSnapshot>);
+
+impl UndoHistory {
+    pub fn push(&mut self, snapshot: BoardSnapshot) {
+        if self.0.len() >= MAX_UNDO {
+            self.0.pop_front();
+        }
+        self.0.push_back(snapshot);
+    }
+
+    pub fn pop(&mut self) -> Option<BoardSnapshot> {
+        self.0.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+fn clear_history(
+    mut change_level_events: EventReader<ChangeLevelEvent>,
+    mut history: ResMut<UndoHistory>,
+) {
+    if change_level_events.iter().next().is_some() {
+        history.clear();
+    }
+}
+
+pub fn handle_undo(
+    mut commands: Commands,
+    mut undo_events: EventReader<UndoEvent>,
+    mut history: ResMut<UndoHistory>,
+    mut draggables: Query<(
+        &mut Transform,
+        &mut Velocity,
+        &mut Draggable,
+        Option<&Children>,
+    )>,
+    padlock_query: Query<With<Padlock>>,
+    win_timer: Query<Entity, With<WinTimer>>,
+) {
+    let Some(snapshot) = undo_events.iter().filter_map(|_| history.pop()).last() else {return;};
+
+    for entity in win_timer.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for shape in snapshot.0 {
+        if let Ok((mut transform, mut velocity, mut draggable, children)) =
+            draggables.get_mut(shape.entity)
+        {
+            // Any padlock will be recreated when the draggable state changes
+            if let Some(children) = children {
+                for &child in children.iter() {
+                    if padlock_query.contains(child) {
+                        commands.entity(child).despawn();
+                    }
+                }
+            }
+
+            *transform = shape.transform;
+            *velocity = shape.velocity;
+            *draggable = shape.draggable;
+        }
+    }
+}
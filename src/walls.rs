@@ -1,4 +1,6 @@
+use bevy::window::WindowResized;
 use bevy_prototype_lyon::shapes::Rectangle;
+use serde::*;
 
 use crate::*;
 
@@ -11,57 +13,260 @@ pub struct WallsPlugin;
 
 impl Plugin for WallsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_walls.after(setup));
+        app.init_resource::<PlayArea>()
+            .add_system(update_play_area)
+            .add_system(spawn_walls.after(update_play_area))
+            .add_system(update_floor.after(update_play_area))
+            .add_system(keep_shapes_inside.after(update_play_area));
     }
 }
 
-fn spawn_walls(mut commands: Commands) {
-    let color = Color::GRAY;
+/// The part of the world inside the walls, which is the size of the window
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PlayArea {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for PlayArea {
+    fn default() -> Self {
+        Self {
+            width: crate::WINDOW_WIDTH,
+            height: crate::WINDOW_HEIGHT,
+        }
+    }
+}
+
+/// The smallest the play area can be, so there is still room to build
+pub const MIN_PLAY_AREA_SIZE: f32 = crate::shape_maker::SHAPE_SIZE * 4.0;
+
+impl PlayArea {
+    /// The play area for a window of this size
+    pub fn from_window(width: f32, height: f32) -> Self {
+        Self {
+            width: width.max(MIN_PLAY_AREA_SIZE),
+            height: height.max(MIN_PLAY_AREA_SIZE),
+        }
+    }
+
+    pub fn half_extents(&self) -> Vec2 {
+        Vec2::new(self.width, self.height) * 0.5
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.clamp(point) == point
+    }
+
+    /// The nearest point inside the play area
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        point.clamp(-self.half_extents(), self.half_extents())
+    }
+}
+
+/// The number of points the floor height is given at
+const FLOOR_SAMPLES: usize = 37;
+/// The highest the floor rises above the bottom of the window
+const FLOOR_RISE: f32 = 60.0;
+const WALL_COLOR: Color = Color::GRAY;
+
+/// The shape of the floor of a level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FloorProfile {
+    #[default]
+    Flat,
+    /// Rising from left to right
+    Slope,
+    Bumps,
+    /// Raised at the sides with a dip in the middle
+    Pit,
+}
+
+impl FloorProfile {
+    /// The height of the floor above the bottom of the window, at evenly spaced points from left to right
+    pub fn heights(&self) -> Vec<f32> {
+        (0..FLOOR_SAMPLES)
+            .map(|i| {
+                let x = i as f32 / (FLOOR_SAMPLES - 1) as f32;
+                match self {
+                    FloorProfile::Flat => 0.0,
+                    FloorProfile::Slope => x * FLOOR_RISE,
+                    FloorProfile::Bumps => {
+                        (1.0 - (x * std::f32::consts::TAU * 3.0).cos()) * 0.25 * FLOOR_RISE
+                    }
+                    FloorProfile::Pit => {
+                        if (x - 0.5).abs() < 0.25 {
+                            0.0
+                        } else {
+                            FLOOR_RISE
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// The heightfield floor, which is also a `Wall`
+#[derive(Component)]
+pub struct Floor(pub FloorProfile);
+
+/// The position, width, height and whether it is horizontal for each wall
+pub fn wall_rectangles(play_area: &PlayArea) -> [(Vec2, f32, f32, bool); 4] {
     const OFFSET: f32 = crate::WALL_WIDTH / 2.0;
     const EXTRA_WIDTH: f32 = crate::WALL_WIDTH * 2.0;
 
-    let bottom_wall_pos: Vec2 = Vec2::new(0.0, -crate::WINDOW_HEIGHT / 2.0 - OFFSET);
-    let top_wall_pos: Vec2 = Vec2::new(0.0, crate::WINDOW_HEIGHT / 2.0 + OFFSET);
-    let left_wall_pos: Vec2 = Vec2::new(-crate::WINDOW_WIDTH / 2.0 - OFFSET, 0.0);
-    let right_wall_pos: Vec2 = Vec2::new(crate::WINDOW_WIDTH / 2.0 + OFFSET, 0.0);
-
-    spawn_wall(
-        &mut commands,
-        bottom_wall_pos,
-        crate::WINDOW_WIDTH + EXTRA_WIDTH,
-        crate::WALL_WIDTH,
-        color,
-        true,
-        // "Bottom-Wall".to_string(),
-    );
-    spawn_wall(
-        &mut commands,
-        top_wall_pos,
-        crate::WINDOW_WIDTH + EXTRA_WIDTH,
-        crate::WALL_WIDTH,
-        color,
-        true,
-        // "Top-Wall".to_string(),
-    );
-
-    spawn_wall(
-        &mut commands,
-        left_wall_pos,
-        crate::WALL_WIDTH,
-        crate::WINDOW_HEIGHT,
-        color,
-        false,
-        // "Left-Wall".to_string(),
-    );
-    spawn_wall(
-        &mut commands,
-        right_wall_pos,
-        crate::WALL_WIDTH,
-        crate::WINDOW_HEIGHT,
-        color,
-        false,
-        // "Right-Wall".to_string(),
-    );
+    let bottom_wall_pos: Vec2 = Vec2::new(0.0, -play_area.height / 2.0 - OFFSET);
+    let top_wall_pos: Vec2 = Vec2::new(0.0, play_area.height / 2.0 + OFFSET);
+    let left_wall_pos: Vec2 = Vec2::new(-play_area.width / 2.0 - OFFSET, 0.0);
+    let right_wall_pos: Vec2 = Vec2::new(play_area.width / 2.0 + OFFSET, 0.0);
+
+    [
+        (
+            bottom_wall_pos,
+            play_area.width + EXTRA_WIDTH,
+            crate::WALL_WIDTH,
+            true,
+        ),
+        (
+            top_wall_pos,
+            play_area.width + EXTRA_WIDTH,
+            crate::WALL_WIDTH,
+            true,
+        ),
+        (left_wall_pos, crate::WALL_WIDTH, play_area.height, false),
+        (right_wall_pos, crate::WALL_WIDTH, play_area.height, false),
+    ]
+}
+
+/// Resize the play area to fit the window
+fn update_play_area(
+    mut resize_events: EventReader<WindowResized>,
+    mut play_area: ResMut<PlayArea>,
+) {
+    let Some(event) = resize_events.iter().last() else {return;};
+    let new_area = PlayArea::from_window(event.width, event.height);
+    if *play_area != new_area {
+        *play_area = new_area;
+    }
+}
+
+/// Replace the walls and the rescue sensors outside them whenever the play area changes
+fn spawn_walls(
+    mut commands: Commands,
+    play_area: Res<PlayArea>,
+    walls: Query<Entity, (Or<(With<Wall>, With<RescueSensor>)>, Without<Floor>)>,
+) {
+    if !play_area.is_changed() {
+        return;
+    }
+    for entity in walls.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (point, width, height, horizontal) in wall_rectangles(&play_area) {
+        spawn_wall(&mut commands, point, width, height, WALL_COLOR, horizontal);
+    }
+
+    let outside_walls = PlayArea {
+        width: play_area.width + crate::WALL_WIDTH * 2.0,
+        height: play_area.height + crate::WALL_WIDTH * 2.0,
+    };
+    for (point, width, height, _) in wall_rectangles(&outside_walls) {
+        spawn_rescue_sensor(&mut commands, point, width, height);
+    }
+}
+
+/// Move shapes which the walls have moved past back inside
+pub fn keep_shapes_inside(
+    play_area: Res<PlayArea>,
+    mut shapes: Query<&mut Transform, With<Draggable>>,
+) {
+    if !play_area.is_changed() {
+        return;
+    }
+    let margin = Vec2::splat(crate::shape_maker::SHAPE_SIZE * 0.5);
+    let max = (play_area.half_extents() - margin).max(Vec2::ZERO);
+
+    for mut transform in shapes.iter_mut() {
+        let position = transform.translation.truncate();
+        let clamped = position.clamp(-max, max);
+        if clamped != position {
+            transform.translation = clamped.extend(transform.translation.z);
+        }
+    }
+}
+
+/// Replace the floor when the play area changes, or the level changes to one with a different profile
+fn update_floor(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    campaign: Res<Campaign>,
+    play_area: Res<PlayArea>,
+    floors: Query<(Entity, &Floor)>,
+) {
+    if !current_level.is_changed() && !play_area.is_changed() {
+        return;
+    }
+
+    let profile = match current_level.0.level_type {
+        LevelType::Campaign(index) => campaign
+            .get_level(index)
+            .map(|x| x.floor)
+            .unwrap_or_default(),
+        _ => FloorProfile::Flat,
+    };
+
+    if !play_area.is_changed() && floors.iter().any(|(_, floor)| floor.0 == profile) {
+        return;
+    }
+    for (entity, _) in floors.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_floor(&mut commands, profile, &play_area);
+}
+
+/// Spawn a heightfield floor along the bottom of the window.
+/// A heightfield has no thickness, so the bottom wall is left beneath it.
+fn spawn_floor(commands: &mut Commands, profile: FloorProfile, play_area: &PlayArea) {
+    let heights = profile.heights();
+    let left = -play_area.width / 2.0;
+    let step = play_area.width / (heights.len() - 1) as f32;
+
+    let mut points: Vec<Vec2> = heights
+        .iter()
+        .enumerate()
+        .map(|(i, height)| Vec2::new(left + step * i as f32, *height))
+        .collect();
+    points.push(Vec2::new(-left, 0.0));
+    points.push(Vec2::new(left, 0.0));
+
+    let collider_shape = Collider::heightfield(heights, Vec2::new(play_area.width, 1.0));
+
+    commands
+        .spawn(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points,
+                closed: true,
+            },
+            DrawMode::Outlined {
+                fill_mode: bevy_prototype_lyon::prelude::FillMode::color(WALL_COLOR),
+                outline_mode: StrokeMode::color(WALL_COLOR),
+            },
+            Transform::default(),
+        ))
+        .insert(RigidBody::Fixed)
+        .insert(Transform::from_translation(Vec3::new(
+            0.0,
+            -play_area.height / 2.0,
+            0.0,
+        )))
+        .insert(collider_shape.clone())
+        .insert(Wall { horizontal: true })
+        .insert(Floor(profile))
+        .with_children(|f| {
+            f.spawn(collider_shape)
+                .insert(Sensor {})
+                .insert(ActiveEvents::COLLISION_EVENTS);
+        });
 }
 
 fn spawn_wall(
@@ -100,3 +305,120 @@ fn spawn_wall(
                 ;
         });
 }
+
+/// Spawn an invisible sensor which catches shapes that have got through the walls
+fn spawn_rescue_sensor(commands: &mut Commands, point: Vec2, width: f32, height: f32) {
+    commands
+        .spawn(TransformBundle::from_transform(
+            Transform::from_translation(point.extend(0.0)),
+        ))
+        .insert(RigidBody::Fixed)
+        .insert(Collider::cuboid(width / 2.0, height / 2.0))
+        .insert(Sensor {})
+        .insert(RescueSensor);
+}
+
+/// Spawn a platform which moves on a schedule.
+/// A seesaw is a dynamic plank joined to a fixed pivot by a motor, the others are kinematic.
+pub fn spawn_platform(commands: &mut Commands, platform: Platform, started: f64) {
+    const PLATFORM_COLOR: Color = Color::DARK_GRAY;
+
+    let shape = Rectangle {
+        extents: platform.size(),
+        origin: RectangleOrigin::Center,
+    };
+    let collider_shape = Collider::cuboid(shape.extents.x / 2.0, shape.extents.y / 2.0);
+    let (position, angle) = platform.pose_at(0.0);
+    let transform = Transform::from_translation(position.extend(0.0))
+        .with_rotation(Quat::from_rotation_z(angle));
+
+    let plank = commands
+        .spawn(GeometryBuilder::build_as(
+            &shape,
+            DrawMode::Outlined {
+                fill_mode: bevy_prototype_lyon::prelude::FillMode::color(PLATFORM_COLOR),
+                outline_mode: StrokeMode::color(PLATFORM_COLOR),
+            },
+            transform,
+        ))
+        .insert(collider_shape)
+        .insert(MovingPlatform { platform, started })
+        .id();
+
+    if platform.is_kinematic() {
+        commands
+            .entity(plank)
+            .insert(RigidBody::KinematicPositionBased);
+    } else {
+        let pivot = commands
+            .spawn(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius: platform.height * 0.25,
+                    center: Vec2::ZERO,
+                },
+                DrawMode::Fill(bevy_prototype_lyon::prelude::FillMode::color(Color::BLACK)),
+                Transform::from_translation(position.extend(1.0)),
+            ))
+            .insert(RigidBody::Fixed)
+            .insert(PlatformPivot)
+            .id();
+
+        commands
+            .entity(plank)
+            .insert(RigidBody::Dynamic)
+            .insert(ImpulseJoint::new(
+                pivot,
+                RevoluteJointBuilder::new().motor_position(angle, SEESAW_STIFFNESS, SEESAW_DAMPING),
+            ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pit_is_lowest_in_the_middle() {
+        let heights = FloorProfile::Pit.heights();
+
+        assert_eq!(heights.len(), FLOOR_SAMPLES);
+        assert_eq!(heights[FLOOR_SAMPLES / 2], 0.0);
+        assert_eq!(heights[0], FLOOR_RISE);
+        assert_eq!(heights[FLOOR_SAMPLES - 1], FLOOR_RISE);
+    }
+
+    #[test]
+    fn test_walls_surround_play_area() {
+        let play_area = PlayArea {
+            width: 800.0,
+            height: 600.0,
+        };
+        let [bottom, top, left, right] = wall_rectangles(&play_area);
+
+        assert_eq!(bottom.0.y + bottom.2 / 2.0, -300.0);
+        assert_eq!(top.0.y - top.2 / 2.0, 300.0);
+        assert_eq!(left.0.x + left.1 / 2.0, -400.0);
+        assert_eq!(right.0.x - right.1 / 2.0, 400.0);
+        assert_eq!(
+            play_area.clamp(Vec2::new(1000.0, -50.0)),
+            Vec2::new(400.0, -50.0)
+        );
+    }
+
+    #[test]
+    fn test_play_area_fits_portrait_window() {
+        let play_area = PlayArea::from_window(300.0, 800.0);
+
+        assert_eq!(play_area.half_extents(), Vec2::new(150.0, 400.0));
+        assert_eq!(PlayArea::from_window(100.0, 800.0).width, MIN_PLAY_AREA_SIZE);
+    }
+
+    #[test]
+    fn test_play_area_contains() {
+        let play_area = PlayArea::from_window(300.0, 800.0);
+
+        assert!(play_area.contains(Vec2::new(150.0, -400.0)));
+        assert!(!play_area.contains(Vec2::new(0.0, -401.0)));
+        assert!(!play_area.contains(Vec2::new(-2000.0, 0.0)));
+    }
+}
@@ -1,13 +1,14 @@
 use bevy::log::{error, info};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Mutex;
 
 use js_sys::{Array, Date, Promise, Uint8Array};
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Blob, BlobPropertyBag, Document, FileReader, Window};
+use web_sys::{Blob, BlobPropertyBag, Document, FileReader, HtmlInputElement, Window};
 
 macro_rules! null_return {
     ($nullable: expr, $message: literal) => {
@@ -102,7 +103,7 @@ pub fn focus_on_first_of_type(element_type: &str) {
     .focus();
 }
 
-async fn download_bytes_inner(file_name: PathBuf, bytes: Vec<u8>) {
+async fn download_bytes_inner(file_name: PathBuf, bytes: Vec<u8>, mime_type: &'static str) {
     let bytes = bytes.as_slice();
     let js_byte_array = Uint8Array::from(bytes);
     let js_array = Array::new();
@@ -111,7 +112,7 @@ async fn download_bytes_inner(file_name: PathBuf, bytes: Vec<u8>) {
     let blob = err_return!(
         Blob::new_with_u8_array_sequence_and_options(
             &js_array,
-            BlobPropertyBag::new().type_("image/png"),
+            BlobPropertyBag::new().type_(mime_type),
         ),
         "Failed to create screenshot blob data"
     );
@@ -154,8 +155,56 @@ async fn download_bytes_inner(file_name: PathBuf, bytes: Vec<u8>) {
     info!("Saving image to path {}", file_name.display());
 }
 
-pub fn download_bytes(file_name: PathBuf, bytes: Vec<u8>) {
-    wasm_bindgen_futures::spawn_local(download_bytes_inner(file_name, bytes));
+pub fn download_bytes(file_name: PathBuf, bytes: Vec<u8>, mime_type: &'static str) {
+    wasm_bindgen_futures::spawn_local(download_bytes_inner(file_name, bytes, mime_type));
+}
+
+/// Text from files the user has uploaded which has not yet been handled
+static UPLOADED_TEXT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Ask the user to pick a text file. Its contents can be retrieved with `pop_uploaded_text`
+pub fn pick_text_file() {
+    let window: Window = null_return!(
+        web_sys::window(),
+        "Didn't find a window to attach to while picking file"
+    );
+    let document: Document = null_return!(
+        window.document(),
+        "Window did not contain a document to attach to while picking file"
+    );
+    let element = err_return!(
+        document.create_element("input"),
+        "Could not create file input"
+    );
+    let input = err_return!(
+        element.dyn_into::<HtmlInputElement>(),
+        "Could not get interactable version of file input"
+    );
+    input.set_type("file");
+    input.set_accept(".txt,text/plain");
+
+    let closure_input = input.clone();
+    let closure = Closure::once(move || {
+        let files = null_return!(closure_input.files(), "File input had no files");
+        let file = null_return!(files.get(0), "No file was chosen");
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let text = err_return!(JsFuture::from(file.text()).await, "Could not read file");
+            let text = null_return!(text.as_string(), "File did not contain text");
+
+            if let Ok(mut uploaded) = UPLOADED_TEXT.lock() {
+                uploaded.push(text);
+            }
+        });
+    });
+
+    input.set_onchange(Some(closure.as_ref().unchecked_ref()));
+    closure.forget();
+    input.click();
+}
+
+pub fn pop_uploaded_text() -> Option<String> {
+    UPLOADED_TEXT.lock().ok()?.pop()
 }
 
 pub fn get_now() -> f64 {
@@ -1,4 +1,5 @@
 pub mod download;
+pub mod share;
 
 use crate::input::{convert_screen_to_world_position, InputDetector};
 use crate::*;
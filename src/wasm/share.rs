@@ -0,0 +1,72 @@
+use bevy::log::{error, info, warn};
+use js_sys::{Function, Object, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Navigator;
+
+/// Share text with the Web Share API, or copy it to the clipboard if that is not available
+pub fn share_text(text: String) {
+    wasm_bindgen_futures::spawn_local(share_text_inner(text));
+}
+
+async fn share_text_inner(text: String) {
+    let Some(window) = web_sys::window() else {
+        error!("Didn't find a window while sharing");
+        return;
+    };
+    let navigator = window.navigator();
+
+    if let Some(promise) = call_share(&navigator, &text) {
+        match JsFuture::from(promise).await {
+            Ok(_) => {
+                info!("Shared result");
+                return;
+            }
+            Err(e) => {
+                // The user closing the share sheet is not a reason to copy instead
+                if error_name(&e).as_deref() == Some("AbortError") {
+                    return;
+                }
+                warn!("Could not share; {:?}", e);
+            }
+        }
+    }
+
+    match call_clipboard(&navigator, &text) {
+        Some(promise) => match JsFuture::from(promise).await {
+            Ok(_) => info!("Copied result to clipboard"),
+            Err(e) => error!("Could not copy to clipboard; {:?}", e),
+        },
+        None => error!("Neither sharing nor the clipboard are available"),
+    }
+}
+
+/// Calls `navigator.share({text})` if the browser supports it
+fn call_share(navigator: &Navigator, text: &str) -> Option<Promise> {
+    let share = get_function(navigator, "share")?;
+    let data = Object::new();
+    Reflect::set(&data, &"text".into(), &text.into()).ok()?;
+    share.call1(navigator, &data).ok()?.dyn_into().ok()
+}
+
+/// Calls `navigator.clipboard.writeText(text)` if the browser supports it
+fn call_clipboard(navigator: &Navigator, text: &str) -> Option<Promise> {
+    let clipboard = Reflect::get(navigator, &"clipboard".into()).ok()?;
+    if clipboard.is_undefined() {
+        return None;
+    }
+    let write_text = get_function(&clipboard, "writeText")?;
+    write_text
+        .call1(&clipboard, &text.into())
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+fn get_function(target: &JsValue, name: &str) -> Option<Function> {
+    Reflect::get(target, &name.into()).ok()?.dyn_into().ok()
+}
+
+fn error_name(error: &JsValue) -> Option<String> {
+    Reflect::get(error, &"name".into()).ok()?.as_string()
+}
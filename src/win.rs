@@ -1,11 +1,11 @@
 use bevy::ecs::event::Events;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use bevy_rapier2d::rapier::crossbeam::atomic::AtomicCell;
-use bevy_rapier2d::rapier::prelude::{EventHandler, PhysicsPipeline};
 
 use crate::game_shape::GameShapeBody;
+use crate::platforms::SimulatedPlatform;
 use crate::screenshots::SaveSVGEvent;
+use crate::simulation::PhysicsWorld;
 use crate::*;
 
 #[derive(Component)]
@@ -35,6 +35,10 @@ pub fn check_for_win(
     level: Res<CurrentLevel>,
     mut new_game_events: EventWriter<ChangeLevelEvent>,
     mut screenshot_events: EventWriter<SaveSVGEvent>,
+    mut split_events: EventWriter<SpeedrunSplitEvent>,
+    mut pkv: ResMut<PkvStore>,
+    playback: Res<ReplayPlayback>,
+    settings: Res<Settings>,
 ) {
     if let Ok((timer_entity, timer, mut timer_transform)) = win_timer.get_single_mut() {
         let remaining = timer.win_time - time.elapsed_seconds_f64();
@@ -44,24 +48,55 @@ pub fn check_for_win(
 
             commands.entity(timer_entity).despawn();
 
+            if playback.is_active() {
+                return; // Watching a replay doesn't count as winning
+            }
+
+            SavedData::update(&mut pkv, |mut x| {
+                x.stats.record_win(&level.0);
+                x
+            });
+
             match level.0.level_type {
                 LevelType::Tutorial => {
                     let title = format!("Equilibrium Tutorial {}", level.0.shapes);
-                    screenshot_events.send(SaveSVGEvent { title });
+                    screenshot_events.send(SaveSVGEvent {
+                        title,
+                        level: level.0,
+                    });
                 }
-                LevelType::Infinite => {
+                LevelType::Infinite { .. } => {
                     let title = format!("Equilibrium Infinite {}", level.0.shapes);
-                    screenshot_events.send(SaveSVGEvent { title });
+                    screenshot_events.send(SaveSVGEvent {
+                        title,
+                        level: level.0,
+                    });
                 }
                 LevelType::Challenge => {
                     let title = format!("Equilibrium Challenge {}", get_today_date());
-                    screenshot_events.send(SaveSVGEvent { title });
+                    screenshot_events.send(SaveSVGEvent {
+                        title,
+                        level: level.0,
+                    });
+                }
+                LevelType::Campaign(index) => {
+                    let title = format!("Equilibrium Campaign {}", index + 1);
+                    screenshot_events.send(SaveSVGEvent {
+                        title,
+                        level: level.0,
+                    });
+                }
+                LevelType::Speedrun(index) => {
+                    split_events.send(SpeedrunSplitEvent {
+                        index,
+                        finished_at: timer.win_time - timer.total_countdown,
+                    });
                 }
                 LevelType::ChallengeComplete(_) => {}
             }
 
             new_game_events.send(ChangeLevelEvent::Next);
-        } else {
+        } else if !settings.reduced_motion {
             let new_scale = (remaining / timer.total_countdown) as f32;
 
             timer_transform.scale = Vec3::new(new_scale, new_scale, 1.0);
@@ -79,6 +114,11 @@ pub fn check_for_tower(
     mut collision_events: ResMut<Events<CollisionEvent>>,
     rapier_context: ResMut<RapierContext>,
     walls: Query<Entity, With<Wall>>,
+    platforms: Query<(
+        &MovingPlatform,
+        &RapierRigidBodyHandle,
+        Option<&RapierImpulseJointHandle>,
+    )>,
 ) {
     if !end_drag_events.iter().any(|_| true) {
         return;
@@ -102,14 +142,27 @@ pub fn check_for_tower(
 
     collision_events.clear();
 
+    let now = time.elapsed_seconds_f64();
+    let simulated_platforms = platforms
+        .iter()
+        .map(|(moving, body, joint)| SimulatedPlatform {
+            platform: moving.platform,
+            body: body.0,
+            joint: joint.map(|x| x.0),
+            elapsed: (now - moving.started) as f32,
+        })
+        .collect();
+
     let will_collide_with_wall = check_future_collisions(
         &rapier_context,
+        simulated_platforms,
         (COUNTDOWN * 2.) as f32,
         (COUNTDOWN * 2. * 60.).floor() as usize,
         GRAVITY,
     );
 
-    let countdown = if will_collide_with_wall {
+    // The tower must be seen to survive the platforms moving
+    let countdown = if will_collide_with_wall || !platforms.is_empty() {
         COUNTDOWN
     } else {
         SHORT_COUNTDOWN
@@ -130,84 +183,21 @@ pub fn check_for_tower(
         );
 }
 
+/// Whether anything will touch a wall soon. Joints are honoured as the whole physics world is cloned.
 fn check_future_collisions(
     context: &RapierContext,
+    platforms: Vec<SimulatedPlatform>,
     dt: f32,
     substeps: usize,
     gravity: Vect,
 ) -> bool {
-    let mut pipeline = PhysicsPipeline::default();
-
-    let mut islands = context.islands.clone();
-    let mut broad_phase = context.broad_phase.clone();
-    let mut narrow_phase = context.narrow_phase.clone();
-    let mut bodies = context.bodies.clone();
-    let mut colliders = context.colliders.clone();
-    let mut impulse_joints = context.impulse_joints.clone();
-    let mut multibody_joints = context.multibody_joints.clone();
-    let mut ccd_solver = context.ccd_solver.clone();
-
-    let mut substep_integration_parameters = context.integration_parameters;
-    substep_integration_parameters.dt = dt / (substeps as Real);
-    let event_handler = SensorCollisionHandler::default();
-    for _i in 0..substeps {
-        pipeline.step(
-            &(gravity / context.physics_scale()).into(),
-            &context.integration_parameters,
-            &mut islands,
-            &mut broad_phase,
-            &mut narrow_phase,
-            &mut bodies,
-            &mut colliders,
-            &mut impulse_joints,
-            &mut multibody_joints,
-            &mut ccd_solver,
-            &(),
-            &event_handler,
-        );
-
-        if event_handler.collisions_found.load() {
-            //      info!("Collision detected after {_i} substeps");
-            return true;
-        }
-    }
+    let mut world = PhysicsWorld::from_context(context);
+    world.integration_parameters.dt = dt / (substeps as Real);
+    world.platforms = platforms;
 
-    //info!("No collision detected after {substeps}");
-    false
-}
-
-#[derive(Default, Debug)]
-struct SensorCollisionHandler {
-    pub collisions_found: AtomicCell<bool>,
-}
-
-impl EventHandler for SensorCollisionHandler {
-    fn handle_collision_event(
-        &self,
-        _bodies: &bevy_rapier2d::rapier::prelude::RigidBodySet,
-        colliders: &bevy_rapier2d::rapier::prelude::ColliderSet,
-        event: bevy_rapier2d::rapier::prelude::CollisionEvent,
-        _contact_pair: Option<&bevy_rapier2d::rapier::prelude::ContactPair>,
-    ) {
-        for c in [event.collider1(), event.collider2()] {
-            if let Some(collider) = colliders.get(c) {
-                if collider.is_sensor() {
-                    self.collisions_found.store(true);
-                }
-            }
-        }
-    }
-
-    fn handle_contact_force_event(
-        &self,
-        _dt: bevy_rapier2d::rapier::prelude::Real,
-        _bodies: &bevy_rapier2d::rapier::prelude::RigidBodySet,
-        _colliders: &bevy_rapier2d::rapier::prelude::ColliderSet,
-        _contact_pair: &bevy_rapier2d::rapier::prelude::ContactPair,
-        _total_force_magnitude: bevy_rapier2d::rapier::prelude::Real,
-    ) {
-        //Do nothing
-    }
+    world
+        .step_until_sensor_collision(gravity / context.physics_scale(), substeps)
+        .is_some()
 }
 
 fn check_for_collisions(
@@ -215,6 +205,7 @@ fn check_for_collisions(
     win_timer: Query<(Entity, &WinTimer)>,
     collision_events: EventReader<CollisionEvent>,
     draggables: Query<&Draggable>,
+    mut pkv: ResMut<PkvStore>,
 ) {
     if win_timer.is_empty() {
         return; // no need to check
@@ -233,5 +224,10 @@ fn check_for_collisions(
     if let Some(_error_message) = fail {
         // scale_time(rapier_config, 1.);
         commands.entity(win_timer.single().0).despawn();
+
+        SavedData::update(&mut pkv, |mut x| {
+            x.stats.failed_win_attempts += 1;
+            x
+        });
     }
 }